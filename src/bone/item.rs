@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::render::DisplayItem;
+
+use super::BoneName;
+
+/// Slot of the entity carrying a model an item mapper can read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EquipmentSlot {
+    MainHand,
+    OffHand,
+    Head,
+    Chest,
+    Legs,
+    Feet,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; 6] = [
+        EquipmentSlot::MainHand,
+        EquipmentSlot::OffHand,
+        EquipmentSlot::Head,
+        EquipmentSlot::Chest,
+        EquipmentSlot::Legs,
+        EquipmentSlot::Feet,
+    ];
+}
+
+/// Items held and worn by the entity carrying a model, empty slots are absent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Equipment {
+    items: HashMap<EquipmentSlot, DisplayItem>,
+}
+
+impl Equipment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, slot: EquipmentSlot) -> Option<&DisplayItem> {
+        self.items.get(&slot)
+    }
+
+    pub fn set(&mut self, slot: EquipmentSlot, item: Option<DisplayItem>) {
+        match item {
+            Some(item) => self.items.insert(slot, item),
+            None => self.items.remove(&slot),
+        };
+    }
+
+    pub fn with(mut self, slot: EquipmentSlot, item: DisplayItem) -> Self {
+        self.set(slot, Some(item));
+        self
+    }
+}
+
+/// Item shown by a bone instead of its elements, `None` shows nothing.
+pub type BoneItemMapper = Arc<dyn Fn(&Equipment) -> Option<DisplayItem> + Send + Sync>;
+
+/// Mapper of a built-in tag, a plain function so the tag stays `const`.
+pub type SlotItemMapper = fn(&Equipment) -> Option<DisplayItem>;
+
+pub fn main_hand(equipment: &Equipment) -> Option<DisplayItem> {
+    equipment.get(EquipmentSlot::MainHand).cloned()
+}
+
+pub fn off_hand(equipment: &Equipment) -> Option<DisplayItem> {
+    equipment.get(EquipmentSlot::OffHand).cloned()
+}
+
+pub fn head(equipment: &Equipment) -> Option<DisplayItem> {
+    equipment.get(EquipmentSlot::Head).cloned()
+}
+
+pub fn chest(equipment: &Equipment) -> Option<DisplayItem> {
+    equipment.get(EquipmentSlot::Chest).cloned()
+}
+
+pub fn legs(equipment: &Equipment) -> Option<DisplayItem> {
+    equipment.get(EquipmentSlot::Legs).cloned()
+}
+
+pub fn feet(equipment: &Equipment) -> Option<DisplayItem> {
+    equipment.get(EquipmentSlot::Feet).cloned()
+}
+
+/// Mapper of the first tag of `bone` having one.
+pub fn mapper_of(bone: &BoneName) -> Option<BoneItemMapper> {
+    bone.tags().iter().find_map(|tag| tag.item_mapper())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use crate::bone::BoneName;
+    use crate::bone::BoneTag;
+    use crate::render::DisplayItem;
+
+    use super::Equipment;
+    use super::EquipmentSlot;
+    use super::mapper_of;
+
+    #[test]
+    fn test_mapper() {
+        let sword = DisplayItem::new("minecraft:diamond_sword");
+        let equipment = Equipment::new().with(EquipmentSlot::MainHand, sword.clone());
+
+        let hand = BoneName::new(
+            HashSet::from([BoneTag::MAIN_HAND]),
+            "hand".to_string(),
+            "mh_hand".to_string(),
+        );
+        let mapper = mapper_of(&hand).unwrap();
+        assert_eq!(mapper(&equipment), Some(sword));
+        assert_eq!(mapper(&Equipment::new()), None);
+
+        let helmet = BoneName::new(
+            HashSet::from([BoneTag::HELMET]),
+            "hat".to_string(),
+            "hm_hat".to_string(),
+        );
+        assert_eq!(mapper_of(&helmet).unwrap()(&equipment), None);
+
+        let plain = BoneName::new(HashSet::new(), "arm".to_string(), "arm".to_string());
+        assert!(mapper_of(&plain).is_none());
+    }
+
+    #[test]
+    fn test_owned_mapper() {
+        let shield = DisplayItem::new("minecraft:shield");
+        let mapped = shield.clone();
+        let tag = BoneTag::owned(
+            "shield",
+            ["sh"],
+            Some(Arc::new(move |_: &Equipment| Some(mapped.clone()))),
+        );
+        let bone = BoneName::new(
+            HashSet::from([tag]),
+            "shield".to_string(),
+            "sh_shield".to_string(),
+        );
+        assert_eq!(mapper_of(&bone).unwrap()(&Equipment::new()), Some(shield));
+    }
+}
@@ -1,5 +1,7 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt::Display;
 use std::hash::Hash;
 use std::sync::Arc;
 use std::sync::LazyLock;
@@ -7,107 +9,309 @@ use std::sync::RwLock;
 
 use tokio::sync::Mutex;
 
-// Ta structure BoneTag
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+use crate::config::BoneTagConfig;
+
+pub mod item;
+pub mod skin;
+
+pub use item::BoneItemMapper;
+use item::SlotItemMapper;
+
+/// Short tags of a `BoneTag`, static for the built-in tags and owned for the ones registered at runtime.
+#[derive(Clone, Debug)]
+enum ShortTags {
+    Static(&'static [&'static str]),
+    Owned(Vec<String>),
+}
+
+/// Item mapper of a tag, a plain function for the built-in tags and shared for the ones registered at runtime.
+#[derive(Clone)]
+enum TagItemMapper {
+    Static(SlotItemMapper),
+    Shared(BoneItemMapper),
+}
+
+impl std::fmt::Debug for TagItemMapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagItemMapper::Static(_) => f.write_str("Static"),
+            TagItemMapper::Shared(_) => f.write_str("Shared"),
+        }
+    }
+}
+
+/// Prefix of a bone name giving it a behavior, written as one of its short `tags` (`h_head`).
+#[derive(Clone, Debug)]
 pub struct BoneTag {
-    pub name: &'static str,
-    pub tags: &'static [&'static str],
-    pub item_mapper: Option<BoneItemMapper>,
+    name: Cow<'static, str>,
+    tags: ShortTags,
+    item_mapper: Option<TagItemMapper>,
+}
+
+// Deux tags sont égaux s'ils ont le même nom
+impl PartialEq for BoneTag {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
 }
 
-// Juste un alias pour exemple, adapte selon ton code
-pub type BoneItemMapper = fn();
+impl Eq for BoneTag {}
+
+impl Hash for BoneTag {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
 
 // Constantes BoneTag
 impl BoneTag {
     pub const HEAD: BoneTag = BoneTag::new("head", &["h"], None);
     pub const HEAD_WITH_CHILDREN: BoneTag = BoneTag::new("head_with_children", &["hi"], None);
-    pub const HITBOX: BoneTag = BoneTag::new("hitbox", &["b", "ob"], None);
+    pub const HITBOX: BoneTag = BoneTag::new("hitbox", &["b"], None);
+    // Hitbox tournant avec son os
+    pub const ORIENTED_HITBOX: BoneTag = BoneTag::new("oriented_hitbox", &["ob"], None);
     pub const SEAT: BoneTag = BoneTag::new("seat", &["p"], None);
     pub const SUB_SEAT: BoneTag = BoneTag::new("sub_seat", &["sp"], None);
+    // Os dessinés avec le skin du joueur
+    pub const PLAYER_HEAD: BoneTag = BoneTag::new("player_head", &["ph"], None);
+    pub const PLAYER_BODY: BoneTag = BoneTag::new("player_body", &["pb"], None);
+    pub const PLAYER_RIGHT_ARM: BoneTag = BoneTag::new("player_right_arm", &["pra"], None);
+    pub const PLAYER_LEFT_ARM: BoneTag = BoneTag::new("player_left_arm", &["pla"], None);
+    pub const PLAYER_RIGHT_LEG: BoneTag = BoneTag::new("player_right_leg", &["prl"], None);
+    pub const PLAYER_LEFT_LEG: BoneTag = BoneTag::new("player_left_leg", &["pll"], None);
+    // Os affichant un objet de l'entité à la place de leurs éléments
+    pub const MAIN_HAND: BoneTag = BoneTag::new("main_hand", &["mh"], Some(item::main_hand));
+    pub const OFF_HAND: BoneTag = BoneTag::new("off_hand", &["oh"], Some(item::off_hand));
+    pub const HELMET: BoneTag = BoneTag::new("helmet", &["hm"], Some(item::head));
+    pub const CHESTPLATE: BoneTag = BoneTag::new("chestplate", &["cp"], Some(item::chest));
+    pub const LEGGINGS: BoneTag = BoneTag::new("leggings", &["lg"], Some(item::legs));
+    pub const BOOTS: BoneTag = BoneTag::new("boots", &["bt"], Some(item::feet));
+
+    pub const BUILT_IN: [BoneTag; 18] = [
+        BoneTag::HEAD,
+        BoneTag::HEAD_WITH_CHILDREN,
+        BoneTag::HITBOX,
+        BoneTag::ORIENTED_HITBOX,
+        BoneTag::SEAT,
+        BoneTag::SUB_SEAT,
+        BoneTag::PLAYER_HEAD,
+        BoneTag::PLAYER_BODY,
+        BoneTag::PLAYER_RIGHT_ARM,
+        BoneTag::PLAYER_LEFT_ARM,
+        BoneTag::PLAYER_RIGHT_LEG,
+        BoneTag::PLAYER_LEFT_LEG,
+        BoneTag::MAIN_HAND,
+        BoneTag::OFF_HAND,
+        BoneTag::HELMET,
+        BoneTag::CHESTPLATE,
+        BoneTag::LEGGINGS,
+        BoneTag::BOOTS,
+    ];
 
     pub const fn new(
         name: &'static str,
         tags: &'static [&'static str],
+        item_mapper: Option<SlotItemMapper>,
+    ) -> BoneTag {
+        BoneTag {
+            name: Cow::Borrowed(name),
+            tags: ShortTags::Static(tags),
+            item_mapper: match item_mapper {
+                Some(mapper) => Some(TagItemMapper::Static(mapper)),
+                None => None,
+            },
+        }
+    }
+
+    /// Tag defined at runtime, from a config or another plugin.
+    pub fn owned<T: Into<String>>(
+        name: impl Into<String>,
+        tags: impl IntoIterator<Item = T>,
         item_mapper: Option<BoneItemMapper>,
     ) -> BoneTag {
         BoneTag {
-            name,
-            tags,
-            item_mapper,
+            name: Cow::Owned(name.into()),
+            tags: ShortTags::Owned(tags.into_iter().map(Into::into).collect()),
+            item_mapper: item_mapper.map(TagItemMapper::Shared),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        let (borrowed, owned): (&[&str], &[String]) = match &self.tags {
+            ShortTags::Static(tags) => (tags, &[]),
+            ShortTags::Owned(tags) => (&[], tags),
+        };
+        borrowed
+            .iter()
+            .copied()
+            .chain(owned.iter().map(String::as_str))
+    }
+
+    pub fn item_mapper(&self) -> Option<BoneItemMapper> {
+        match self.item_mapper.as_ref()? {
+            TagItemMapper::Static(mapper) => Some(Arc::new(*mapper)),
+            TagItemMapper::Shared(mapper) => Some(mapper.clone()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BoneTagError {
+    /// A tag with this name is already registered
+    DuplicateName(String),
+    /// The short tag is already claimed by the tag `owner`
+    DuplicateTag { tag: String, owner: String },
+    /// Short tags can't be empty nor contain `_`, which separates them in bone names
+    InvalidTag(String),
+}
+
+impl Display for BoneTagError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoneTagError::DuplicateName(name) => write!(f, "bone tag {name} is already registered"),
+            BoneTagError::DuplicateTag { tag, owner } => {
+                write!(f, "short tag {tag} is already used by the bone tag {owner}")
+            }
+            BoneTagError::InvalidTag(tag) => write!(f, "invalid short tag \"{tag}\""),
         }
     }
 }
 
-// Registry pour BoneTag
+impl std::error::Error for BoneTagError {}
+
+/// Bone tags by name, and the name of the tag owning each short tag.
 pub struct BoneTagRegistry {
-    tags: HashMap<&'static str, BoneTag>,
+    tags: HashMap<String, BoneTag>,
+    short_tags: HashMap<String, String>,
 }
 
 impl BoneTagRegistry {
     pub fn new() -> Self {
         Self {
             tags: HashMap::new(),
+            short_tags: HashMap::new(),
         }
     }
 
-    pub fn register(&mut self, tag: BoneTag) {
-        self.tags.insert(tag.name, tag);
+    /// Adds `tag`, unless its name or one of its short tags is already taken.
+    /// Models read before keep their bone names until `ModelRegistry::reparse`.
+    pub fn register(&mut self, tag: BoneTag) -> Result<(), BoneTagError> {
+        if self.tags.contains_key(tag.name()) {
+            return Err(BoneTagError::DuplicateName(tag.name().to_string()));
+        }
+        for short in tag.tags() {
+            if short.is_empty() || short.contains('_') {
+                return Err(BoneTagError::InvalidTag(short.to_string()));
+            }
+            if let Some(owner) = self.short_tags.get(short) {
+                return Err(BoneTagError::DuplicateTag {
+                    tag: short.to_string(),
+                    owner: owner.clone(),
+                });
+            }
+        }
+        for short in tag.tags() {
+            self.short_tags
+                .insert(short.to_string(), tag.name().to_string());
+        }
+        self.tags.insert(tag.name().to_string(), tag);
+        Ok(())
+    }
+
+    pub fn unregister(&mut self, name: &str) -> Option<BoneTag> {
+        let tag = self.tags.remove(name)?;
+        self.short_tags.retain(|_, owner| owner != name);
+        Some(tag)
     }
 
     pub fn get(&self, name: &str) -> Option<&BoneTag> {
         self.tags.get(name)
     }
 
+    /// Tag owning the short tag `tag`.
+    pub fn by_short_tag(&self, tag: &str) -> Option<&BoneTag> {
+        self.short_tags
+            .get(tag)
+            .and_then(|name| self.tags.get(name))
+    }
+
+    /// Every registered tag, sorted by name.
+    pub fn tags(&self) -> Vec<&BoneTag> {
+        let mut tags = self.tags.values().collect::<Vec<_>>();
+        tags.sort_by(|a, b| a.name().cmp(b.name()));
+        tags
+    }
+
+    /// Reads the short tags prefixing `raw_name`, `h_b_jaw` is the bone `jaw` with the `head` and `hitbox` tags.
+    /// The last segment always belongs to the name, and an empty segment ends the tags so `_b_frame` is the untagged
+    /// bone `b_frame` and `h__b_frame` the head `b_frame`.
     pub fn parse(&self, raw_name: &str) -> BoneName {
-        let tag_array = raw_name.split("_").collect::<Vec<_>>();
-        if tag_array.len() < 2 {
+        let segments = raw_name.split('_').collect::<Vec<_>>();
+        let mut tags = HashSet::new();
+        let mut start = 0;
+        while start + 1 < segments.len() {
+            let segment = segments[start];
+            if segment.is_empty() {
+                start += 1;
+                break;
+            }
+            let Some(tag) = self.by_short_tag(&segment.to_ascii_lowercase()) else {
+                break;
+            };
+            tags.insert(tag.clone());
+            start += 1;
+        }
+        let name = segments[start..].join("_");
+        if name.is_empty() {
             return BoneName::new(HashSet::new(), raw_name.to_string(), raw_name.to_string());
         }
-        let mut set: HashSet<BoneTag> = HashSet::new();
-        for tag in &tag_array {
-            if set.len() < tag_array.len()
-                && let Some(tag) = self.get(tag)
-            {
-                set.insert(tag.clone());
-            } else {
-                let name = tag_array[set.len()..tag_array.len()].join("_");
-                return BoneName::new(set, name, raw_name.to_string());
-            }
+        BoneName::new(tags, name, raw_name.to_string())
+    }
+}
+
+impl Default for BoneTagRegistry {
+    /// Registry of the `BoneTag::BUILT_IN` tags.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        for tag in BoneTag::BUILT_IN {
+            registry
+                .register(tag)
+                .expect("built-in bone tags don't share short tags");
         }
-        BoneName::new(set, raw_name.to_string(), raw_name.to_string())
+        registry
     }
 }
 
 // Singleton registry global
 pub static BONE_TAG_REGISTRY: LazyLock<Arc<RwLock<BoneTagRegistry>>> =
-    LazyLock::new(|| Arc::new(RwLock::new(BoneTagRegistry::new())));
+    LazyLock::new(|| Arc::new(RwLock::new(BoneTagRegistry::default())));
 
 pub fn get_registry() -> Arc<RwLock<BoneTagRegistry>> {
     BONE_TAG_REGISTRY.clone()
 }
 
-// Macro pour enregistrer plusieurs tags facilement
-macro_rules! register_bone_tags {
-    ( $( $tag:expr ),* $(,)? ) => {
-        {
-            let registry = get_registry();
-            let mut w = registry.write().unwrap();
-            $(
-                w.register($tag);
-            )*
+/// Registers the tags of the config next to the built-in ones and logs them all.
+/// Called when the plugin loads, before any model is read, as bone names are parsed with the tags known then.
+pub fn initialize_bone_tags(tags: &[BoneTagConfig]) {
+    let registry = get_registry();
+    let mut registry = registry.write().unwrap();
+    for tag in tags {
+        if let Err(e) = registry.register(tag.to_tag()) {
+            log::warn!("Skipping bone tag {} of the config: {}", tag.name, e);
         }
-    };
-}
-
-// Fonction d'initialisation à appeler au démarrage
-fn initialize_bone_tags() {
-    register_bone_tags!(
-        BoneTag::HEAD,
-        BoneTag::HEAD_WITH_CHILDREN,
-        BoneTag::HITBOX,
-        BoneTag::SEAT,
-        BoneTag::SUB_SEAT,
+    }
+    log::info!(
+        "Registered bone tags: {}",
+        registry
+            .tags()
+            .iter()
+            .map(|t| t.name())
+            .collect::<Vec<_>>()
+            .join(", ")
     );
 }
 
@@ -141,4 +345,125 @@ impl BoneName {
             raw_name,
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn raw_name(&self) -> &str {
+        &self.raw_name
+    }
+
+    pub fn tags(&self) -> &HashSet<BoneTag> {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &BoneTag) -> bool {
+        self.tags.contains(tag)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::BoneTag;
+    use super::BoneTagError;
+    use super::BoneTagRegistry;
+
+    #[test]
+    fn test_register() {
+        let mut registry = BoneTagRegistry::default();
+        assert_eq!(registry.tags().len(), BoneTag::BUILT_IN.len());
+        assert_eq!(registry.by_short_tag("ob"), Some(&BoneTag::ORIENTED_HITBOX));
+
+        let glow = BoneTag::owned("glow", ["gl", "gw"], None);
+        assert_eq!(registry.register(glow.clone()), Ok(()));
+        assert_eq!(registry.by_short_tag("gw"), Some(&glow));
+        assert!(registry.tags().contains(&&glow));
+
+        assert_eq!(
+            registry.register(BoneTag::owned("glow", ["x"], None)),
+            Err(BoneTagError::DuplicateName("glow".to_string()))
+        );
+        assert_eq!(
+            registry.register(BoneTag::owned("hat", ["h"], None)),
+            Err(BoneTagError::DuplicateTag {
+                tag: "h".to_string(),
+                owner: "head".to_string(),
+            })
+        );
+        assert_eq!(
+            registry.register(BoneTag::owned("bad", ["a_b"], None)),
+            Err(BoneTagError::InvalidTag("a_b".to_string()))
+        );
+        assert!(registry.get("hat").is_none());
+
+        assert_eq!(registry.unregister("glow"), Some(glow));
+        assert!(registry.by_short_tag("gl").is_none());
+    }
+
+    fn parsed(raw_name: &str) -> (HashSet<BoneTag>, String) {
+        let name = BoneTagRegistry::default().parse(raw_name);
+        assert_eq!(name.raw_name(), raw_name);
+        (name.tags().clone(), name.name().to_string())
+    }
+
+    #[test]
+    fn test_parse() {
+        let tags = |tags: &[BoneTag]| tags.iter().cloned().collect::<HashSet<_>>();
+        assert_eq!(
+            parsed("h_head"),
+            (tags(&[BoneTag::HEAD]), "head".to_string())
+        );
+        assert_eq!(
+            parsed("hi_neck"),
+            (tags(&[BoneTag::HEAD_WITH_CHILDREN]), "neck".to_string())
+        );
+        assert_eq!(
+            parsed("h_b_jaw"),
+            (tags(&[BoneTag::HEAD, BoneTag::HITBOX]), "jaw".to_string())
+        );
+        assert_eq!(
+            parsed("ob_body"),
+            (tags(&[BoneTag::ORIENTED_HITBOX]), "body".to_string())
+        );
+        assert_eq!(
+            parsed("p_seat"),
+            (tags(&[BoneTag::SEAT]), "seat".to_string())
+        );
+        assert_eq!(
+            parsed("sp_back"),
+            (tags(&[BoneTag::SUB_SEAT]), "back".to_string())
+        );
+        assert_eq!(
+            parsed("pra_arm"),
+            (tags(&[BoneTag::PLAYER_RIGHT_ARM]), "arm".to_string())
+        );
+        assert_eq!(
+            parsed("mh_hand_item"),
+            (tags(&[BoneTag::MAIN_HAND]), "hand_item".to_string())
+        );
+        assert_eq!(
+            parsed("H_head"),
+            (tags(&[BoneTag::HEAD]), "head".to_string())
+        );
+
+        // Sans tag
+        assert_eq!(parsed("head"), (tags(&[]), "head".to_string()));
+        assert_eq!(parsed("left_arm"), (tags(&[]), "left_arm".to_string()));
+        assert_eq!(parsed("arm_h_b"), (tags(&[]), "arm_h_b".to_string()));
+        assert_eq!(parsed("h"), (tags(&[]), "h".to_string()));
+
+        // Le dernier segment reste le nom
+        assert_eq!(parsed("h_b"), (tags(&[BoneTag::HEAD]), "b".to_string()));
+
+        // Un segment vide termine les tags
+        assert_eq!(parsed("_b_frame"), (tags(&[]), "b_frame".to_string()));
+        assert_eq!(
+            parsed("h__b_frame"),
+            (tags(&[BoneTag::HEAD]), "b_frame".to_string())
+        );
+        assert_eq!(parsed("h_"), (tags(&[]), "h_".to_string()));
+    }
 }
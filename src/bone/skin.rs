@@ -0,0 +1,107 @@
+use super::BoneName;
+use super::BoneTag;
+
+/// Part of a player skin a bone can be drawn with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SkinPart {
+    Head,
+    Body,
+    RightArm,
+    LeftArm,
+    RightLeg,
+    LeftLeg,
+}
+
+impl SkinPart {
+    pub const ALL: [SkinPart; 6] = [
+        SkinPart::Head,
+        SkinPart::Body,
+        SkinPart::RightArm,
+        SkinPart::LeftArm,
+        SkinPart::RightLeg,
+        SkinPart::LeftLeg,
+    ];
+
+    pub fn tag(&self) -> BoneTag {
+        match self {
+            SkinPart::Head => BoneTag::PLAYER_HEAD,
+            SkinPart::Body => BoneTag::PLAYER_BODY,
+            SkinPart::RightArm => BoneTag::PLAYER_RIGHT_ARM,
+            SkinPart::LeftArm => BoneTag::PLAYER_LEFT_ARM,
+            SkinPart::RightLeg => BoneTag::PLAYER_RIGHT_LEG,
+            SkinPart::LeftLeg => BoneTag::PLAYER_LEFT_LEG,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SkinPart::Head => "head",
+            SkinPart::Body => "body",
+            SkinPart::RightArm => "right_arm",
+            SkinPart::LeftArm => "left_arm",
+            SkinPart::RightLeg => "right_leg",
+            SkinPart::LeftLeg => "left_leg",
+        }
+    }
+
+    /// Size in skin pixels, width, height then depth as in the box UV layout of the skin.
+    pub fn size(&self) -> [f32; 3] {
+        match self {
+            SkinPart::Head => [8.0, 8.0, 8.0],
+            SkinPart::Body => [8.0, 12.0, 4.0],
+            _ => [4.0, 12.0, 4.0],
+        }
+    }
+
+    /// Number read by the limb shader of the pack, 0 for the head drawn as is.
+    pub fn marker(&self) -> i32 {
+        Self::ALL.iter().position(|p| p == self).unwrap_or_default() as i32
+    }
+
+    /// Part drawn by `bone`, from its tags or else from its name (`head`, `RightArm`, `left_leg`...).
+    pub fn of(bone: &BoneName) -> Option<Self> {
+        if let Some(part) = Self::ALL.into_iter().find(|p| bone.has_tag(&p.tag())) {
+            return Some(part);
+        }
+        let name = bone
+            .name()
+            .to_ascii_lowercase()
+            .replace(['_', '-', ' '], "");
+        Self::ALL.into_iter().find(|p| {
+            p.name().replace('_', "") == name || (name == "torso" && *p == SkinPart::Body)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::bone::BoneName;
+    use crate::bone::BoneTag;
+
+    use super::SkinPart;
+
+    #[test]
+    fn test_of() {
+        let named = |name: &str| BoneName::new(HashSet::new(), name.to_string(), name.to_string());
+        assert_eq!(SkinPart::of(&named("RightArm")), Some(SkinPart::RightArm));
+        assert_eq!(SkinPart::of(&named("left_leg")), Some(SkinPart::LeftLeg));
+        assert_eq!(SkinPart::of(&named("torso")), Some(SkinPart::Body));
+        assert_eq!(SkinPart::of(&named("tail")), None);
+
+        let tagged = BoneName::new(
+            HashSet::from([BoneTag::PLAYER_LEFT_ARM]),
+            "sleeve".to_string(),
+            "pla_sleeve".to_string(),
+        );
+        assert_eq!(SkinPart::of(&tagged), Some(SkinPart::LeftArm));
+    }
+
+    #[test]
+    fn test_marker() {
+        assert_eq!(SkinPart::Head.marker(), 0);
+        assert_eq!(SkinPart::Body.marker(), 1);
+        assert_eq!(SkinPart::LeftLeg.marker(), 5);
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::bone::BoneItemMapper;
+use crate::bone::BoneTag;
+use crate::bone::item::Equipment;
+use crate::bone::item::EquipmentSlot;
+use crate::pack::AtlasMode;
+use crate::pack::DEFAULT_MAX_ATLAS_SIZE;
+
+pub const CONFIG_FILE: &str = "config.json";
+
+/// Settings of the plugin, read from `config.json` in its data folder. Missing fields keep their default.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct PluginConfig {
+    pub pack: PackConfig,
+    /// Registered with the built-in tags before the models are read
+    pub bone_tags: Vec<BoneTagConfig>,
+}
+
+/// Bone tag defined by the server, `{"name": "lantern", "tags": ["ln"], "slot": "off_hand"}`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BoneTagConfig {
+    pub name: String,
+    pub tags: Vec<String>,
+    /// Slot whose item the bone shows instead of its elements
+    pub slot: Option<EquipmentSlot>,
+}
+
+impl BoneTagConfig {
+    pub fn to_tag(&self) -> BoneTag {
+        let mapper = self.slot.map(|slot| {
+            Arc::new(move |equipment: &Equipment| equipment.get(slot).cloned()) as BoneItemMapper
+        });
+        BoneTag::owned(self.name.clone(), self.tags.iter().cloned(), mapper)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PackConfig {
+    pub atlas: AtlasMode,
+    /// Largest width and height of an atlas in pixels, the textures are written separately past it
+    pub max_atlas_size: u32,
+}
+
+impl Default for PackConfig {
+    fn default() -> Self {
+        Self {
+            atlas: AtlasMode::default(),
+            max_atlas_size: DEFAULT_MAX_ATLAS_SIZE,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "failed to access the config: {e}"),
+            ConfigError::Json(e) => write!(f, "invalid config json: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            ConfigError::Json(e) => Some(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(value: std::io::Error) -> Self {
+        ConfigError::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(value: serde_json::Error) -> Self {
+        ConfigError::Json(value)
+    }
+}
+
+impl PluginConfig {
+    /// Reads the config at `path`, writing the default one there first if it does not exist.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        if !path.exists() {
+            let config = Self::default();
+            std::fs::write(path, serde_json::to_vec_pretty(&config)?)?;
+            return Ok(config);
+        }
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::bone::item::Equipment;
+    use crate::bone::item::EquipmentSlot;
+    use crate::pack::AtlasMode;
+    use crate::pack::DEFAULT_MAX_ATLAS_SIZE;
+    use crate::render::DisplayItem;
+
+    use super::PluginConfig;
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join("modelblock_test_config.json");
+        let _ = fs::remove_file(&path);
+        assert_eq!(PluginConfig::load(&path).unwrap(), PluginConfig::default());
+        assert!(path.exists());
+
+        fs::write(&path, r#"{"pack": {"atlas": "per_model"}}"#).unwrap();
+        let config = PluginConfig::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.pack.atlas, AtlasMode::PerModel);
+        assert_eq!(config.pack.max_atlas_size, DEFAULT_MAX_ATLAS_SIZE);
+        assert!(config.bone_tags.is_empty());
+    }
+
+    #[test]
+    fn test_bone_tags() {
+        let config: PluginConfig = serde_json::from_str(
+            r#"{"bone_tags": [{"name": "lantern", "tags": ["ln"], "slot": "off_hand"}, {"name": "glow", "tags": ["gw"]}]}"#,
+        )
+        .unwrap();
+        let lantern = DisplayItem::new("minecraft:lantern");
+        let equipment = Equipment::new().with(EquipmentSlot::OffHand, lantern.clone());
+
+        let tag = config.bone_tags[0].to_tag();
+        assert_eq!(tag.name(), "lantern");
+        assert_eq!(tag.tags().collect::<Vec<_>>(), ["ln"]);
+        assert_eq!(tag.item_mapper().unwrap()(&equipment), Some(lantern));
+        assert!(config.bone_tags[1].to_tag().item_mapper().is_none());
+    }
+}
@@ -13,17 +13,25 @@ use serde::Serialize;
 use crate::bone::BONE_TAG_REGISTRY;
 use crate::bone::BoneName;
 use crate::data::blueprint::BlueprintChildren;
+use crate::data::raw::model::DataPoint;
 use crate::data::raw::model::KeyFrameChannel;
 use crate::data::raw::model::ModelAnimation;
 use crate::data::raw::model::ModelKeyFrame;
 use crate::utils::VectorPoint;
 use crate::utils::interpolate::VectorInterpolation;
 use crate::utils::math;
+use crate::utils::molang::MolangContext;
+use crate::utils::point_at;
 use crate::utils::sum;
+use crate::utils::sum_with_set;
 
 use super::BlueprintGroup;
+use super::script::AnimationScript;
 use super::script::BlueprintScript;
+use super::script::ScriptEffect;
+use super::script::ScriptError;
 use super::script::TimeScript;
+use super::script::get_script_registry;
 
 #[derive(Clone, Default)]
 pub struct AnimationMovement {
@@ -66,6 +74,22 @@ impl AnimationMovement {
         }
     }
 
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn position(&self) -> Option<Vector3<f32>> {
+        self.position
+    }
+
+    pub fn rotation(&self) -> Option<Vector3<f32>> {
+        self.rotation
+    }
+
+    pub fn scale(&self) -> Option<Vector3<f32>> {
+        self.scale
+    }
+
     pub fn has_keyframe(&self) -> bool {
         self.position.is_some() || self.scale.is_some() || self.rotation.is_some()
     }
@@ -87,6 +111,11 @@ impl AnimationMovement {
 pub struct BlueprintAnimator {
     pub name: String,
     pub key_frame: Vec<AnimationMovement>,
+    /// Keyframes of each channel, sampled with their own interpolation between the baked frames
+    pub position: Vec<VectorPoint>,
+    pub rotation: Vec<VectorPoint>,
+    pub scale: Vec<VectorPoint>,
+    pub dynamic: DynamicAnimator,
 }
 
 impl BlueprintAnimator {
@@ -98,6 +127,91 @@ impl BlueprintAnimator {
 pub struct AnimatorData {
     pub name: String,
     pub points: Vec<AnimationPoint>,
+    pub position: Vec<VectorPoint>,
+    pub rotation: Vec<VectorPoint>,
+    pub scale: Vec<VectorPoint>,
+    pub dynamic: DynamicAnimator,
+}
+
+/// Keyframe of a channel using Molang, evaluated again on every sample.
+#[derive(Clone)]
+struct DynamicFrame {
+    time: f32,
+    value: DataPoint,
+    convert: fn(Vector3<f32>) -> Vector3<f32>,
+    interpolation: Arc<dyn VectorInterpolation + Sync + Send>,
+}
+
+impl DynamicFrame {
+    fn point(&self, context: &MolangContext) -> VectorPoint {
+        VectorPoint::new(
+            (self.convert)(self.value.evaluate(context)),
+            self.time,
+            self.interpolation.clone(),
+        )
+    }
+}
+
+/// Channels of an animator with at least one Molang keyframe, every keyframe of those channels is kept.
+#[derive(Clone, Default)]
+pub struct DynamicAnimator {
+    position: Vec<DynamicFrame>,
+    rotation: Vec<DynamicFrame>,
+    scale: Vec<DynamicFrame>,
+}
+
+impl DynamicAnimator {
+    const fn new() -> Self {
+        Self {
+            position: Vec::new(),
+            rotation: Vec::new(),
+            scale: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position.is_empty() && self.rotation.is_empty() && self.scale.is_empty()
+    }
+
+    /// Drops the channels whose keyframes were all folded to constants.
+    fn retain_dynamic(&mut self) {
+        for channel in [&mut self.position, &mut self.rotation, &mut self.scale] {
+            retain_unique(channel, |frame| frame.time);
+            if channel.iter().all(|frame| frame.value.is_constant()) {
+                channel.clear();
+            }
+        }
+    }
+
+    fn sample(frames: &[DynamicFrame], time: f32, context: &MolangContext) -> Option<Vector3<f32>> {
+        if frames.is_empty() {
+            return None;
+        }
+        let points = frames
+            .iter()
+            .map(|frame| frame.point(context))
+            .collect::<Vec<_>>();
+        Some(match points.iter().position(|p| p.time >= time) {
+            None => points[points.len() - 1].vector,
+            Some(0) => points[0].vector,
+            Some(i) if points[i].time == time => points[i].vector,
+            Some(i) => {
+                points[i - 1]
+                    .interpolation
+                    .interpolate(&points, i, time)
+                    .vector
+            }
+        })
+    }
+}
+
+fn position_to_display(vec: Vector3<f32>) -> Vector3<f32> {
+    let m = math::MODEL_TO_BLOCK_MULTIPLIER;
+    math::transform_to_display(Vector3::new(vec.x / m, vec.y / m, vec.z / m))
+}
+
+fn scale_to_display(vec: Vector3<f32>) -> Vector3<f32> {
+    vec.sub(&Vector3::new(1.0, 1.0, 1.0))
 }
 
 pub struct BlueprintAnimatorBuilder {
@@ -105,6 +219,11 @@ pub struct BlueprintAnimatorBuilder {
     transform: Vec<VectorPoint>,
     scale: Vec<VectorPoint>,
     rotation: Vec<VectorPoint>,
+    /// Bone the sound and particle keyframes are played on
+    bone: Option<BoneName>,
+    scripts: Vec<TimeScript>,
+    script_errors: Vec<ScriptError>,
+    dynamic: DynamicAnimator,
 }
 
 impl BlueprintAnimatorBuilder {
@@ -114,72 +233,207 @@ impl BlueprintAnimatorBuilder {
             transform: Vec::new(),
             scale: Vec::new(),
             rotation: Vec::new(),
+            bone: None,
+            scripts: Vec::new(),
+            script_errors: Vec::new(),
+            dynamic: DynamicAnimator::new(),
         }
     }
 
+    pub fn bone(&mut self, bone: BoneName) -> &mut Self {
+        self.bone = Some(bone);
+        self
+    }
+
+    /// Sound, particle and timeline keyframes added so far.
+    pub fn take_scripts(&mut self) -> Vec<TimeScript> {
+        take(&mut self.scripts)
+    }
+
+    /// Invalid lines of the timeline keyframes added so far.
+    pub fn take_script_errors(&mut self) -> Vec<ScriptError> {
+        take(&mut self.script_errors)
+    }
+
     pub fn add_frame(&mut self, keyframe: &ModelKeyFrame) -> &mut Self {
         if keyframe.time > self.length {
             return self;
         }
 
-        let interpolation: Arc<dyn VectorInterpolation + Sync + Send + 'static> =
-            keyframe.find_interpolation();
+        // Les expressions Molang sont évaluées une première fois au temps de la clé
+        let context = MolangContext::at(keyframe.time);
         for data_point in &keyframe.data_points {
-            let vec = data_point.to_vector();
-            let div = |a: Vector3<f32>, b: f32| Vector3::new(a.x / b, a.y / b, a.z / b);
+            let vec = data_point.evaluate(&context);
+            let frame = |convert: fn(Vector3<f32>) -> Vector3<f32>, interpolation| DynamicFrame {
+                time: keyframe.time,
+                value: data_point.clone(),
+                convert,
+                interpolation,
+            };
             match keyframe.channel {
-                KeyFrameChannel::Position => self.transform.push(VectorPoint::new(
-                    math::transform_to_display(div(vec, math::MODEL_TO_BLOCK_MULTIPLIER)),
-                    keyframe.time,
-                    interpolation.clone(),
-                )),
-                KeyFrameChannel::Rotation => self.rotation.push(VectorPoint::new(
-                    math::animation_to_display(vec),
-                    keyframe.time,
-                    interpolation.clone(),
-                )),
-                KeyFrameChannel::Scale => self.scale.push(VectorPoint::new(
-                    vec.sub(&Vector3::new(1.0, 1.0, 1.0)),
+                KeyFrameChannel::Position => {
+                    let interpolation = keyframe.find_interpolation(position_to_display);
+                    self.dynamic
+                        .position
+                        .push(frame(position_to_display, interpolation.clone()));
+                    self.transform.push(VectorPoint::new(
+                        position_to_display(vec),
+                        keyframe.time,
+                        interpolation,
+                    ))
+                }
+                KeyFrameChannel::Rotation => {
+                    let interpolation = keyframe.find_interpolation(math::animation_to_display);
+                    self.dynamic
+                        .rotation
+                        .push(frame(math::animation_to_display, interpolation.clone()));
+                    self.rotation.push(VectorPoint::new(
+                        math::animation_to_display(vec),
+                        keyframe.time,
+                        interpolation,
+                    ))
+                }
+                KeyFrameChannel::Scale => {
+                    let interpolation = keyframe.find_interpolation(|v| v);
+                    self.dynamic
+                        .scale
+                        .push(frame(scale_to_display, interpolation.clone()));
+                    self.scale.push(VectorPoint::new(
+                        scale_to_display(vec),
+                        keyframe.time,
+                        interpolation,
+                    ))
+                }
+                KeyFrameChannel::Timeline => {
+                    if data_point.script().trim().is_empty() {
+                        continue;
+                    }
+                    // Les actions sont résolues à l'exécution, l'analyse ne sert qu'à signaler les erreurs
+                    let (_, errors) = get_script_registry()
+                        .read()
+                        .unwrap()
+                        .parse(data_point.script());
+                    self.script_errors.extend(errors);
+                    self.scripts
+                        .push(TimeScript::timeline(keyframe.time, data_point.script()));
+                }
+                KeyFrameChannel::Sound => self.add_effect(
                     keyframe.time,
-                    interpolation.clone(),
-                )),
-                _ => {}
+                    data_point.effect(),
+                    ScriptEffect::Sound,
+                    None,
+                ),
+                KeyFrameChannel::Particle => {
+                    let locator = (!data_point.locator().is_empty()).then(|| {
+                        BONE_TAG_REGISTRY
+                            .read()
+                            .unwrap()
+                            .parse(data_point.locator())
+                    });
+                    self.add_effect(
+                        keyframe.time,
+                        data_point.effect(),
+                        ScriptEffect::Particle,
+                        locator,
+                    )
+                }
             }
         }
 
         self
     }
 
+    fn add_effect(
+        &mut self,
+        time: f32,
+        name: &str,
+        effect: fn(String) -> ScriptEffect,
+        locator: Option<BoneName>,
+    ) {
+        if name.is_empty() {
+            return;
+        }
+        let bone = locator.or_else(|| self.bone.clone());
+        self.scripts
+            .push(AnimationScript::effect(effect(name.to_string()), bone).time(time));
+    }
+
     pub fn build(&mut self, name: impl Into<String>) -> AnimatorData {
+        let position = unique(take(&mut self.transform));
+        let rotation = unique(take(&mut self.rotation));
+        let scale = unique(take(&mut self.scale));
+        let mut dynamic = take(&mut self.dynamic);
+        dynamic.retain_dynamic();
         AnimatorData {
             name: name.into(),
-            points: sum(
-                self.length,
-                &unique(take(&mut self.transform)),
-                &unique(take(&mut self.rotation)),
-                &unique(take(&mut self.scale)),
-            ),
+            points: sum(self.length, &position, &rotation, &scale),
+            position,
+            rotation,
+            scale,
+            dynamic,
         }
     }
 }
 
-fn unique(items: Vec<VectorPoint>) -> Vec<VectorPoint> {
-    use std::collections::HashSet;
-    let mut seen = HashSet::new();
-
+fn unique(mut items: Vec<VectorPoint>) -> Vec<VectorPoint> {
+    retain_unique(&mut items, |vp| vp.time);
     items
-        .into_iter() // itère par valeur, ownership pris
-        .filter(|vp| seen.insert(OrderedFloat(vp.time))) // garde si temps jamais vu
-        .collect()
+}
+
+/// Keeps the first keyframe of each time, shared by the constant and Molang channels so they pick the same one.
+fn retain_unique<T>(items: &mut Vec<T>, time: impl Fn(&T) -> f32) {
+    let mut seen = std::collections::HashSet::new();
+    items.retain(|item| seen.insert(OrderedFloat(time(item))));
 }
 
 impl BlueprintAnimator {
-    pub fn iterator(&self, r#type: AnimationType) -> Arc<dyn AnimationIterator> {
+    pub fn iterator(&self, r#type: AnimationType) -> Box<dyn AnimationIterator> {
         r#type.create(self.key_frame.iter().map(|x| x.clone().into()).collect())
     }
+
+    /// Movement at `time`, each channel following the interpolation of its keyframes.
+    pub fn sample(&self, time: f32) -> AnimationMovement {
+        let at = |movement: &AnimationMovement| AnimationMovement {
+            time,
+            skip_interpolation: None,
+            ..movement.clone()
+        };
+        let (a, b) = match self.key_frame.iter().position(|k| k.time > time) {
+            None => return self.key_frame.last().map(at).unwrap_or_default(),
+            Some(0) => return at(&self.key_frame[0]),
+            Some(i) => (&self.key_frame[i - 1], &self.key_frame[i]),
+        };
+        if b.skip_interpolation == Some(true) || b.time <= a.time {
+            return at(a);
+        }
+        AnimationMovement::new(
+            time,
+            point_at(&self.position, time),
+            point_at(&self.scale, time),
+            point_at(&self.rotation, time),
+            None,
+        )
+    }
+
+    /// `sample` with the Molang channels evaluated again, `context.anim_time` being the animation time.
+    pub fn sample_with(&self, time: f32, context: &MolangContext) -> AnimationMovement {
+        let movement = self.sample(time);
+        if self.dynamic.is_empty() {
+            return movement;
+        }
+        let dynamic = |frames: &[DynamicFrame], fallback: Option<Vector3<f32>>| {
+            DynamicAnimator::sample(frames, time, context).or(fallback)
+        };
+        AnimationMovement {
+            position: dynamic(&self.dynamic.position, movement.position),
+            rotation: dynamic(&self.dynamic.rotation, movement.rotation),
+            scale: dynamic(&self.dynamic.scale, movement.scale),
+            ..movement
+        }
+    }
 }
 
-trait AnimationIterator: Iterator<Item = Timed> {
+pub trait AnimationIterator: Iterator<Item = Timed> + Send + Sync {
     fn r#type(&self) -> AnimationType;
 }
 
@@ -190,7 +444,7 @@ struct PlayOnce {
 
 impl AnimationIterator for PlayOnce {
     fn r#type(&self) -> AnimationType {
-        todo!()
+        AnimationType::PlayOnce
     }
 }
 
@@ -210,11 +464,28 @@ impl Iterator for PlayOnce {
 struct Loop {
     key_frame: Vec<Timed>,
     index: usize,
+    looped: bool,
+    /// Whether something else than a keyframe at 0 is left to play after a wrap
+    skip_zero: bool,
+}
+
+impl Loop {
+    fn new(key_frame: Vec<Timed>) -> Self {
+        let skip_zero = key_frame
+            .iter()
+            .any(|t| t.time() > 0.0 || matches!(t, Timed::Script(_)));
+        Self {
+            key_frame,
+            index: 0,
+            looped: false,
+            skip_zero,
+        }
+    }
 }
 
 impl AnimationIterator for Loop {
     fn r#type(&self) -> AnimationType {
-        todo!()
+        AnimationType::Loop
     }
 }
 
@@ -222,18 +493,58 @@ impl Iterator for Loop {
     type Item = Timed;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index >= self.key_frame.len() {
-            self.index = 1;
+        if self.key_frame.is_empty() {
+            return None;
         }
-        let item = self.key_frame[self.index].clone();
-        self.index += 1;
+        loop {
+            if self.index >= self.key_frame.len() {
+                self.index = 0;
+                self.looped = true;
+            }
+            let item = &self.key_frame[self.index];
+            self.index += 1;
+            // La frame à 0 est identique à la dernière, seuls les scripts à 0 sont rejoués
+            if self.looped
+                && self.skip_zero
+                && matches!(item, Timed::KeyFrame(_))
+                && item.time() <= 0.0
+            {
+                continue;
+            }
+            return Some(item.clone());
+        }
+    }
+}
+
+struct HoldOnLast {
+    key_frame: Vec<Timed>,
+    index: usize,
+}
+
+impl AnimationIterator for HoldOnLast {
+    fn r#type(&self) -> AnimationType {
+        AnimationType::HoldOnLast
+    }
+}
+
+impl Iterator for HoldOnLast {
+    type Item = Timed;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self
+            .key_frame
+            .get(self.index)
+            .or_else(|| self.key_frame.last())?
+            .clone();
+        self.index = (self.index + 1).min(self.key_frame.len());
         Some(item)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum AnimationType {
     #[default]
+    #[serde(rename = "once")]
     PlayOnce,
     #[serde(rename = "loop")]
     Loop,
@@ -247,6 +558,15 @@ pub enum Timed {
     KeyFrame(AnimationMovement),
 }
 
+impl Timed {
+    pub fn time(&self) -> f32 {
+        match self {
+            Timed::Script(script) => script.time(),
+            Timed::KeyFrame(movement) => movement.time,
+        }
+    }
+}
+
 impl From<AnimationMovement> for Timed {
     fn from(value: AnimationMovement) -> Self {
         Self::KeyFrame(value)
@@ -260,14 +580,29 @@ impl From<TimeScript> for Timed {
 }
 
 impl AnimationType {
-    fn create(&self, key_frames: Vec<Timed>) -> Arc<dyn AnimationIterator> {
+    /// Playback time after moving `delta` seconds, negative when played backwards.
+    /// `None` once a `once` animation went past either end.
+    pub fn advance(&self, time: f32, delta: f32, length: f32) -> Option<f32> {
+        let time = time + delta;
+        match self {
+            AnimationType::PlayOnce => (0.0..=length).contains(&time).then_some(time),
+            AnimationType::Loop if length > 0.0 => Some(time.rem_euclid(length)),
+            AnimationType::Loop => Some(0.0),
+            AnimationType::HoldOnLast => Some(time.clamp(0.0, length.max(0.0))),
+        }
+    }
+
+    pub fn create(&self, key_frames: Vec<Timed>) -> Box<dyn AnimationIterator> {
         match self {
-            AnimationType::PlayOnce => Arc::new(PlayOnce {
-                key_frame: key_frames.clone(),
+            AnimationType::PlayOnce => Box::new(PlayOnce {
+                key_frame: key_frames,
+                index: 0,
+            }),
+            AnimationType::Loop => Box::new(Loop::new(key_frames)),
+            AnimationType::HoldOnLast => Box::new(HoldOnLast {
+                key_frame: key_frames,
                 index: 0,
             }),
-            AnimationType::Loop => todo!(),
-            AnimationType::HoldOnLast => todo!(),
         }
     }
 }
@@ -278,67 +613,93 @@ pub struct BlueprintAnimation {
     lenth: f32,
     overriding: bool,
     animator: HashMap<BoneName, BlueprintAnimator>,
-    script: Option<BlueprintScript>,
+    script: BlueprintScript,
     empty_animator: Vec<AnimationMovement>,
 }
 
 impl BlueprintAnimation {
-    fn from(children: &BlueprintGroup, animation: &ModelAnimation) -> Self {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn loop_type(&self) -> AnimationType {
+        self.loop_type
+    }
+
+    pub fn length(&self) -> f32 {
+        self.lenth
+    }
+
+    /// Whether the animation replaces the lower layers on its bones instead of adding to them.
+    pub fn overriding(&self) -> bool {
+        self.overriding
+    }
+
+    pub fn animator(&self, bone: &BoneName) -> Option<&BlueprintAnimator> {
+        self.animator.get(bone)
+    }
+
+    pub fn animators(&self) -> impl Iterator<Item = (&BoneName, &BlueprintAnimator)> {
+        self.animator.iter()
+    }
+
+    /// Sound, particle and timeline keyframes of every animator.
+    pub fn script(&self) -> &BlueprintScript {
+        &self.script
+    }
+
+    pub fn from(children: &[BlueprintChildren], animation: &ModelAnimation) -> Self {
         let mut map: HashMap<BoneName, AnimatorData> = HashMap::new();
-        let mut blueprint_script: Option<BlueprintScript> = if animation.overriding {
-            None
-        } else {
-            Some(animation.into())
-        };
-        let animator = animation.animators;
-        for (uuid, animator) in animator {
-            let name = if let Some(name) = animator.name {
+        let mut scripts: Vec<TimeScript> = Vec::new();
+        for (uuid, animator) in &animation.animators {
+            let name = if let Some(name) = &animator.name {
                 name
             } else {
                 continue;
             };
+            let effect = animator.kind == "effect" || uuid == "effects";
             let mut builder = BlueprintAnimator::builder(animation.length);
+            let bone = BONE_TAG_REGISTRY.read().unwrap().parse(name);
+            if !effect {
+                builder.bone(bone.clone());
+            }
             let mut keyframes = animator.keyframes.clone();
             keyframes.sort();
             for keyframe in keyframes {
                 builder.add_frame(&keyframe);
             }
-            if uuid == "effect" {
-                blueprint_script = Some(BlueprintScript::from(animation))
-            } else {
-                map.insert(
-                    BONE_TAG_REGISTRY.read().unwrap().parse(&name),
-                    builder.build(name),
-                );
+            scripts.extend(builder.take_scripts());
+            for error in builder.take_script_errors() {
+                log::warn!("Animation {}: {error}", animation.name);
+            }
+            if !effect {
+                map.insert(bone, builder.build(name));
             }
         }
         let animators: HashMap<BoneName, BlueprintAnimator> =
-            AnimationGenerator::create_movements(children, map);
-        let empty_animator = if animators.is_empty() {
-            vec![
+            AnimationGenerator::create_movements(animation.length, children, map);
+        let empty_animator = match animators.values().next() {
+            Some(animator) => animator.key_frame.iter().map(|e| e.empty()).collect(),
+            None => vec![
                 AnimationMovement::default(),
                 AnimationMovement::from_lenght(animation.length),
-            ]
-        } else {
-            animators.values().into()
+            ],
         };
-        let empty_keyframes = empty_animator
-            .first()
-            .map(|e| e.keyframe.into_iter().map(|e| e.empty()).collect());
         Self {
-            name: animation.name,
+            name: animation.name.clone(),
             loop_type: animation.looptype,
             lenth: animation.length,
             overriding: animation.overriding,
             animator: animators,
-            script: blueprint_script,
-            empty_animator: empty_keyframes,
+            script: BlueprintScript::new(animation, scripts),
+            empty_animator,
         }
     }
 }
 
 pub struct AnimationTree {
-    pub parent: Weak<AnimationTree>,       // lien faible vers le parent
+    pub name: BoneName,
+    pub parent: Weak<AnimationTree>, // lien faible vers le parent
     pub children: Vec<Arc<AnimationTree>>, // enfants avec Arc
     pub points: Vec<AnimationPoint>,
 }
@@ -347,58 +708,36 @@ impl AnimationTree {
     pub fn new_root(
         point_map: &HashMap<BoneName, AnimatorData>,
         group: &BlueprintGroup,
-        points: Vec<AnimationPoint>,
     ) -> Arc<Self> {
-        // Création de la racine sans parent
-        let root = Arc::new(Self {
-            parent: Weak::new(),
-            children: vec![],
-            points,
-        });
-
-        // Ajout récursif des enfants
-        let children = Self::build_children(point_map, &root, group);
-        // On remplit les enfants de la racine
-        Arc::get_mut(&mut Arc::clone(&root)).unwrap().children = children;
-
-        root
+        // La racine n'a pas de parent
+        Self::new(point_map, group, Weak::new())
     }
 
-    fn build_children(
+    fn new(
         point_map: &HashMap<BoneName, AnimatorData>,
-        parent: &Arc<AnimationTree>,
         group: &BlueprintGroup,
-    ) -> Vec<Arc<AnimationTree>> {
-        group
-            .children
-            .iter()
-            .filter_map(|g| {
-                if let BlueprintChildren::Group(b) = g {
-                    // Récupération éventuelle des points
-                    let points = point_map
-                        .get(&b.name)
-                        .map(|a| a.points.clone())
-                        .unwrap_or_default();
-
-                    // Création de l’enfant avec Weak vers le parent
-                    let child = Arc::new(Self {
-                        parent: Arc::downgrade(parent),
-                        children: vec![],
-                        points,
-                    });
-
-                    // Construction récursive des enfants de cet enfant
-                    let sub_children = Self::build_children(point_map, &child, b);
-
-                    // Ajout des enfants à l’enfant
-                    Arc::get_mut(&mut Arc::clone(&child)).unwrap().children = sub_children;
-
-                    Some(child)
-                } else {
-                    None
-                }
-            })
-            .collect()
+        parent: Weak<AnimationTree>,
+    ) -> Arc<Self> {
+        // Récupération éventuelle des points
+        let points = point_map
+            .get(&group.name)
+            .map(|a| a.points.clone())
+            .unwrap_or_default();
+        // new_cyclic donne le Weak vers le noeud avant sa construction,
+        // ce qui permet de créer les enfants directement
+        Arc::new_cyclic(|this| Self {
+            name: group.name.clone(),
+            parent,
+            children: group
+                .children
+                .iter()
+                .filter_map(|g| match g {
+                    BlueprintChildren::Group(b) => Some(Self::new(point_map, b, this.clone())),
+                    BlueprintChildren::Element(_) => None,
+                })
+                .collect(),
+            points,
+        })
     }
 
     pub fn flatten_leaves(self: &Arc<Self>) -> Vec<Arc<AnimationTree>> {
@@ -413,6 +752,17 @@ impl AnimationTree {
                 .collect()
         }
     }
+
+    /// Names of this node and all of its ancestors, from the node up to the root.
+    pub fn chain(self: &Arc<Self>) -> Vec<BoneName> {
+        let mut chain = vec![self.name.clone()];
+        let mut parent = self.parent.upgrade();
+        while let Some(node) = parent {
+            chain.push(node.name.clone());
+            parent = node.parent.upgrade();
+        }
+        chain
+    }
 }
 
 pub struct AnimationGenerator {
@@ -421,35 +771,111 @@ pub struct AnimationGenerator {
 }
 
 impl AnimationGenerator {
-    pub fn new(
-        point_map: HashMap<BoneName, AnimatorData>,
-        children: Vec<BlueprintChildren>,
-    ) -> Self {
+    pub fn new(point_map: HashMap<BoneName, AnimatorData>, children: &[BlueprintChildren]) -> Self {
         let trees: Vec<Arc<AnimationTree>> = children
             .iter()
             .filter_map(|g| {
                 if let BlueprintChildren::Group(b) = g {
-                    Some(AnimationTree::new_root(&point_map, b, vec![]))
+                    Some(AnimationTree::new_root(&point_map, b))
                 } else {
                     None
                 }
             })
-            .flat_map(|t| t.flatten_leaves().clone())
+            .flat_map(|t| t.flatten_leaves())
             .collect();
         Self { point_map, trees }
     }
 
+    fn times(&self, name: &BoneName) -> impl Iterator<Item = OrderedFloat<f32>> + '_ {
+        self.point_map
+            .get(name)
+            .into_iter()
+            .flat_map(|a| a.points.iter())
+            .map(|p| OrderedFloat(p.position.time))
+    }
+
+    /// Every bone of a leaf-to-root chain shares the same timestamps, so a child is
+    /// resampled whenever one of its ancestors moves and the composed transform stays aligned.
+    fn chain_times(&self, length: f32) -> HashMap<BoneName, BTreeSet<OrderedFloat<f32>>> {
+        let mut result: HashMap<BoneName, BTreeSet<OrderedFloat<f32>>> = HashMap::new();
+        for leaf in &self.trees {
+            let chain = leaf.chain();
+            let mut set: BTreeSet<OrderedFloat<f32>> =
+                chain.iter().flat_map(|name| self.times(name)).collect();
+            set.insert(OrderedFloat(0.0));
+            set.insert(OrderedFloat(length));
+            for name in chain {
+                result.entry(name).or_default().extend(set.iter().copied());
+            }
+        }
+        result
+    }
+
     pub fn create_movements(
         lenght: f32,
-        children: Vec<BlueprintChildren>,
+        children: &[BlueprintChildren],
         point_map: HashMap<BoneName, AnimatorData>,
     ) -> HashMap<BoneName, BlueprintAnimator> {
         let floatset: BTreeSet<OrderedFloat<f32>> = point_map
             .values()
             .flat_map(|a| a.points.clone())
             .map(|p| OrderedFloat(p.position.time))
+            .chain([OrderedFloat(0.0), OrderedFloat(lenght)])
             .collect();
-        
+        let generator = Self::new(point_map, children);
+        let chain_times = generator.chain_times(lenght);
+        generator
+            .point_map
+            .iter()
+            .map(|(name, data)| {
+                // Un os absent de l'arbre garde l'ensemble global
+                let set = chain_times.get(name).unwrap_or(&floatset).clone();
+                let points = sum_with_set(&data.position, &data.rotation, &data.scale, set);
+                let mut previous: Option<&AnimationPoint> = None;
+                let key_frame = points
+                    .iter()
+                    .map(|point| {
+                        let movement = Self::movement(data, point, previous);
+                        previous = Some(point);
+                        movement
+                    })
+                    .collect();
+                (
+                    name.clone(),
+                    BlueprintAnimator {
+                        name: data.name.clone(),
+                        key_frame,
+                        position: data.position.clone(),
+                        rotation: data.rotation.clone(),
+                        scale: data.scale.clone(),
+                        dynamic: data.dynamic.clone(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn movement(
+        data: &AnimatorData,
+        point: &AnimationPoint,
+        previous: Option<&AnimationPoint>,
+    ) -> AnimationMovement {
+        // Le point précédent décide de la façon d'arriver sur celui-ci
+        let skip = previous.is_some_and(|p| {
+            !p.position.interpolation.is_continuous()
+                || !p.rotation.interpolation.is_continuous()
+                || !p.scale.interpolation.is_continuous()
+        });
+        let channel = |points: &[VectorPoint], value: &VectorPoint| {
+            (!points.is_empty()).then_some(value.vector)
+        };
+        AnimationMovement::new(
+            point.position.time,
+            channel(&data.position, &point.position),
+            channel(&data.scale, &point.scale),
+            channel(&data.rotation, &point.rotation),
+            Some(skip),
+        )
     }
 }
 
@@ -459,3 +885,145 @@ pub struct AnimationPoint {
     pub rotation: VectorPoint,
     pub scale: VectorPoint,
 }
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::data::fixture::bone;
+    use crate::data::fixture::load;
+    use crate::data::raw::model::ModelKeyFrame;
+    use crate::utils::molang::MolangContext;
+
+    use super::super::script::AnimationScript;
+    use super::AnimationMovement;
+    use super::AnimationType;
+    use super::BlueprintAnimator;
+    use super::DynamicAnimator;
+    use super::Timed;
+
+    fn keyframe(time: f32, x: &str) -> ModelKeyFrame {
+        serde_json::from_value(json!({
+            "channel": "position",
+            "data_points": [{ "x": x, "y": 0, "z": 0 }],
+            "uuid": format!("{time}-{x}"),
+            "time": time,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_duplicate_time_keeps_same_frame() {
+        let mut builder = BlueprintAnimator::builder(1.0);
+        for keyframe in [
+            keyframe(0.0, "q.anim_time"),
+            keyframe(0.5, "2"),
+            keyframe(0.5, "3"),
+        ] {
+            builder.add_frame(&keyframe);
+        }
+        let data = builder.build("bone");
+        assert_eq!(data.position.len(), 2);
+        let context = MolangContext::at(0.5);
+        let dynamic = DynamicAnimator::sample(&data.dynamic.position, 0.5, &context).unwrap();
+        assert_eq!(dynamic, data.position[1].vector);
+    }
+
+    #[test]
+    fn test_create_movements() {
+        let blueprint = load();
+        let animation = blueprint.animations.values().next().unwrap();
+
+        // bone4 est enfant de bone2 : ils partagent les mêmes temps
+        let parent = &animation.animator[&bone("bone2")];
+        let child = &animation.animator[&bone("bone4")];
+        let times =
+            |a: &super::BlueprintAnimator| a.key_frame.iter().map(|k| k.time).collect::<Vec<_>>();
+        assert_eq!(times(parent), times(child));
+        assert_eq!(parent.key_frame.first().unwrap().time, 0.0);
+        assert_eq!(parent.key_frame.last().unwrap().time, animation.lenth);
+
+        // bone n'a que des rotations
+        let root = &animation.animator[&bone("bone")];
+        assert!(root.key_frame.iter().all(|k| k.position.is_none()));
+        assert!(root.key_frame.iter().all(|k| k.rotation.is_some()));
+    }
+
+    fn timeline() -> Vec<Timed> {
+        vec![
+            AnimationMovement::from_lenght(0.0).into(),
+            AnimationScript::empty().time(0.25).into(),
+            AnimationMovement::from_lenght(0.5).into(),
+            AnimationScript::empty().time(0.75).into(),
+            AnimationMovement::from_lenght(1.0).into(),
+        ]
+    }
+
+    fn times(iterator: impl Iterator<Item = Timed>, count: usize) -> Vec<f32> {
+        iterator.take(count).map(|t| t.time()).collect()
+    }
+
+    #[test]
+    fn test_play_once() {
+        let iterator = AnimationType::PlayOnce.create(timeline());
+        assert_eq!(iterator.r#type(), AnimationType::PlayOnce);
+        assert_eq!(times(iterator, 10), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn test_loop_wraps_after_first_frame() {
+        let iterator = AnimationType::Loop.create(timeline());
+        assert_eq!(iterator.r#type(), AnimationType::Loop);
+        assert_eq!(
+            times(iterator, 9),
+            vec![0.0, 0.25, 0.5, 0.75, 1.0, 0.25, 0.5, 0.75, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_loop_replays_scripts_at_zero() {
+        let mut timeline = timeline();
+        timeline.insert(1, AnimationScript::empty().time(0.0).into());
+        let iterator = AnimationType::Loop.create(timeline);
+        let kinds = iterator
+            .take(11)
+            .map(|t| (t.time(), matches!(t, Timed::Script(_))))
+            .collect::<Vec<_>>();
+        assert_eq!(&kinds[6..8], &[(0.0, true), (0.25, true)]);
+        assert_eq!(kinds.iter().filter(|(t, _)| *t == 0.0).count(), 3);
+
+        let single = AnimationType::Loop.create(vec![AnimationMovement::from_lenght(0.0).into()]);
+        assert_eq!(times(single, 3), vec![0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_hold_on_last() {
+        let mut iterator = AnimationType::HoldOnLast.create(timeline());
+        assert_eq!(iterator.r#type(), AnimationType::HoldOnLast);
+        assert_eq!(
+            times(&mut iterator, 7),
+            vec![0.0, 0.25, 0.5, 0.75, 1.0, 1.0, 1.0]
+        );
+        assert!(matches!(iterator.next(), Some(Timed::KeyFrame(_))));
+    }
+
+    #[test]
+    fn test_advance_backwards() {
+        let close = |time: Option<f32>, expected: f32| (time.unwrap() - expected).abs() < 1e-5;
+        assert_eq!(AnimationType::PlayOnce.advance(0.5, -0.25, 1.0), Some(0.25));
+        assert_eq!(AnimationType::PlayOnce.advance(0.1, -0.25, 1.0), None);
+        assert!(close(AnimationType::Loop.advance(0.1, -0.25, 1.0), 0.85));
+        assert!(close(AnimationType::Loop.advance(0.9, 0.25, 1.0), 0.15));
+        assert_eq!(
+            AnimationType::HoldOnLast.advance(0.1, -0.25, 1.0),
+            Some(0.0)
+        );
+        assert_eq!(AnimationType::HoldOnLast.advance(0.9, 0.25, 1.0), Some(1.0));
+    }
+
+    #[test]
+    fn test_empty_iterators() {
+        assert!(AnimationType::Loop.create(vec![]).next().is_none());
+        assert!(AnimationType::HoldOnLast.create(vec![]).next().is_none());
+    }
+}
@@ -11,6 +11,8 @@ use pumpkin_util::math::vector3::Vector3;
 
 use crate::bone::BoneName;
 use crate::bone::get_registry;
+use crate::data::error::ModelLoadError;
+use crate::utils::math;
 
 use self::animation::BlueprintAnimation;
 
@@ -25,99 +27,455 @@ use super::raw::model::ModelTexture;
 pub mod animation;
 pub mod script;
 
+/// Name of the bone given to the elements at the root of the outliner.
+pub const ROOT_BONE: &str = "root";
+
 pub struct BlueprintTexture {
-    name: String,
-    image: ImageBuffer<Rgba<u8>, Vec<u8>>,
-    uv_width: i32,
-    uv_height: i32,
+    pub name: String,
+    pub image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    pub uv_width: i32,
+    pub uv_height: i32,
+    /// Set when the image is a vertical strip of several frames
+    pub animation: Option<TextureAnimation>,
+}
+
+/// Frame settings of an animated texture, as written in its `.png.mcmeta`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureAnimation {
+    pub frame_count: u32,
+    pub frame_time: u32,
+    pub interpolate: bool,
+    pub frames: Vec<u32>,
+}
+
+impl TextureAnimation {
+    fn from(
+        value: &ModelTexture,
+        image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    ) -> Result<Option<Self>, ModelLoadError> {
+        let (width, height) = image.dimensions();
+        if value.uv_width == 0 || value.uv_height == 0 {
+            return Ok(None);
+        }
+        // uv_height is the size of one frame in uv units
+        let frame_height = value.uv_height * width / value.uv_width;
+        if frame_height == 0 || height <= frame_height {
+            return Ok(None);
+        }
+        if height % frame_height != 0 {
+            return Err(ModelLoadError::InvalidTextureAnimation {
+                texture: value.name.clone(),
+                height,
+                frame_height,
+            });
+        }
+        let frame_count = height / frame_height;
+        let frames = value
+            .frame_order
+            .split_whitespace()
+            .filter_map(|f| f.parse::<u32>().ok())
+            .filter(|f| *f < frame_count)
+            .collect::<Vec<_>>();
+        Ok(Some(Self {
+            frame_count,
+            frame_time: value.frame_time.max(1),
+            interpolate: value.frame_interpolate,
+            frames: if frames.is_empty() {
+                value.frame_order_type.frames(frame_count)
+            } else {
+                frames
+            },
+        }))
+    }
 }
 
-impl From<&ModelTexture> for BlueprintTexture {
-    fn from(value: &ModelTexture) -> Self {
-        let base64_part = &value
-            .source
-            .split(',')
-            .nth(1)
-            .expect("Invalid data URL format");
-
-        let decoded = BASE64_STANDARD
-            .decode(base64_part)
-            .expect("Base64 decode failed");
-        Self {
+impl TryFrom<&ModelTexture> for BlueprintTexture {
+    type Error = ModelLoadError;
+
+    fn try_from(value: &ModelTexture) -> Result<Self, Self::Error> {
+        let base64_part =
+            value
+                .source
+                .split(',')
+                .nth(1)
+                .ok_or_else(|| ModelLoadError::InvalidTextureUrl {
+                    texture: value.name.clone(),
+                })?;
+
+        let decoded = BASE64_STANDARD.decode(base64_part).map_err(|source| {
+            ModelLoadError::TextureDecode {
+                texture: value.name.clone(),
+                source,
+            }
+        })?;
+        let image = image::load_from_memory(&decoded)
+            .map_err(|source| ModelLoadError::TextureImage {
+                texture: value.name.clone(),
+                source,
+            })?
+            .into_rgba8();
+        Ok(Self {
             name: value.name.clone(),
-            image: image::load_from_memory(&decoded)
-                .expect("Failed to load image")
-                .into_rgba8(),
+            animation: TextureAnimation::from(value, &image)?,
+            image,
             uv_width: value.uv_width as i32,
             uv_height: value.uv_height as i32,
-        }
+        })
     }
 }
 
 pub struct BlueprintGroup {
-    name: BoneName,
-    origin: Float3,
-    rotation: Float3,
-    children: Vec<BlueprintChildren>,
+    pub name: BoneName,
+    pub origin: Float3,
+    pub rotation: Float3,
+    pub children: Vec<BlueprintChildren>,
 }
 
-enum BlueprintChildren {
+impl BlueprintGroup {
+    pub fn elements(&self) -> impl Iterator<Item = &ModelElement> {
+        self.children.iter().filter_map(|c| match c {
+            BlueprintChildren::Element(e) => Some(e),
+            BlueprintChildren::Group(_) => None,
+        })
+    }
+
+    pub fn groups(&self) -> impl Iterator<Item = &BlueprintGroup> {
+        self.children.iter().filter_map(|c| match c {
+            BlueprintChildren::Group(g) => Some(g),
+            BlueprintChildren::Element(_) => None,
+        })
+    }
+
+    /// Elements the pack draws, the visible ones or all of them when none is, the bone then starting hidden.
+    pub fn shown_elements(&self) -> impl Iterator<Item = &ModelElement> {
+        let visible = self.elements().any(|e| e.visibility);
+        self.elements().filter(move |e| e.visibility || !visible)
+    }
+
+    /// False when the bone has elements and all of them are hidden.
+    pub fn visible(&self) -> bool {
+        let mut elements = self.elements().peekable();
+        elements.peek().is_none() || elements.any(|e| e.visibility)
+    }
+}
+
+pub enum BlueprintChildren {
     Element(ModelElement),
     Group(BlueprintGroup),
 }
 
+/// An element whose rotation Minecraft cannot represent, moved into its own bone.
+#[derive(Clone, Debug)]
+pub struct ElementSplit {
+    pub element: String,
+    pub uuid: String,
+    pub rotation: Float3,
+    pub bone: BoneName,
+}
+
 impl BlueprintChildren {
-    pub fn from(value: &ModelChildren, elements: &HashMap<String, ModelElement>) -> Self {
+    pub fn try_from(
+        value: &ModelChildren,
+        elements: &HashMap<String, ModelElement>,
+        splits: &mut Vec<ElementSplit>,
+    ) -> Result<Self, ModelLoadError> {
         match value {
-            ModelChildren::Element(uuid) => {
-                BlueprintChildren::Element(elements.get(&uuid.uuid).unwrap().clone())
-            }
+            ModelChildren::Element(uuid) => elements
+                .get(uuid)
+                .map(|e| BlueprintChildren::Element(e.clone()))
+                .ok_or_else(|| ModelLoadError::UnknownElement { uuid: uuid.clone() }),
             ModelChildren::Group(group) => {
+                let name = get_registry().read().unwrap().parse(&group.name);
                 let child = group
                     .children
                     .iter()
-                    .map(|e| Self::from(e, elements))
-                    .collect();
-                BlueprintChildren::Group(BlueprintGroup {
-                    name: get_registry().read().unwrap().parse(&group.name),
+                    .map(|e| {
+                        Self::try_from(e, elements, splits).map(|c| c.split_rotation(&name, splits))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(BlueprintChildren::Group(BlueprintGroup {
+                    name,
                     origin: group.origin.clone(),
                     rotation: group.rotation.clone(),
                     children: child,
-                })
+                }))
+            }
+        }
+    }
+
+    /// Moves an element Minecraft cannot rotate into a synthetic bone pivoting on the
+    /// element origin, so the rotation is carried by the display transform instead.
+    fn split_rotation(self, parent: &BoneName, splits: &mut Vec<ElementSplit>) -> Self {
+        let element = match self {
+            BlueprintChildren::Element(e) if !math::check_valid_degree(&e.rotation) => e,
+            other => return other,
+        };
+        let raw_name = format!("{}_{}", parent.raw_name(), element.uuid);
+        let bone = BoneName::new(HashSet::new(), raw_name.clone(), raw_name);
+        splits.push(ElementSplit {
+            element: element.name.clone(),
+            uuid: element.uuid.clone(),
+            rotation: element.rotation.clone(),
+            bone: bone.clone(),
+        });
+        BlueprintChildren::Group(BlueprintGroup {
+            name: bone,
+            origin: element.origin.clone(),
+            rotation: element.rotation.clone(),
+            children: vec![BlueprintChildren::Element(ModelElement {
+                rotation: Float3::default(),
+                ..element
+            })],
+        })
+    }
+}
+
+pub struct ModelBlueprint {
+    pub name: String,
+    pub scale: f32,
+    pub resolution: ModelResolution,
+    pub textures: Vec<BlueprintTexture>,
+    pub group: Vec<BlueprintChildren>,
+    pub animations: HashMap<String, BlueprintAnimation>,
+    pub splits: Vec<ElementSplit>,
+}
+
+impl ModelBlueprint {
+    pub fn root_groups(&self) -> impl Iterator<Item = &BlueprintGroup> {
+        self.group.iter().filter_map(|c| match c {
+            BlueprintChildren::Group(g) => Some(g),
+            BlueprintChildren::Element(_) => None,
+        })
+    }
+
+    /// Every group of the model, parents before their children.
+    pub fn bones(&self) -> Vec<&BlueprintGroup> {
+        fn walk<'a>(group: &'a BlueprintGroup, bones: &mut Vec<&'a BlueprintGroup>) {
+            bones.push(group);
+            for child in group.groups() {
+                walk(child, bones);
+            }
+        }
+        let mut bones = Vec::new();
+        for child in &self.group {
+            if let BlueprintChildren::Group(group) = child {
+                walk(group, &mut bones);
             }
         }
+        bones
     }
 }
 
-struct ModelBlueprint {
-    name: String,
-    scale: f32,
-    resolution: ModelResolution,
-    textures: Vec<BlueprintTexture>,
-    group: Vec<BlueprintChildren>,
-    animations: HashMap<String, BlueprintAnimation>,
+/// Bone holding the root elements, named `ROOT_BONE` unless a group already uses that name.
+fn root_bone(groups: &[BlueprintChildren]) -> BoneName {
+    fn taken(children: &[BlueprintChildren], raw_name: &str) -> bool {
+        children.iter().any(|c| match c {
+            BlueprintChildren::Group(g) => {
+                g.name.raw_name() == raw_name || taken(&g.children, raw_name)
+            }
+            BlueprintChildren::Element(_) => false,
+        })
+    }
+    let mut raw_name = ROOT_BONE.to_string();
+    let mut suffix = 1;
+    while taken(groups, &raw_name) {
+        suffix += 1;
+        raw_name = format!("{ROOT_BONE}_{suffix}");
+    }
+    BoneName::new(HashSet::new(), raw_name.clone(), raw_name)
 }
 
-impl From<ModelData> for ModelBlueprint {
-    fn from(data: ModelData) -> Self {
+impl TryFrom<ModelData> for ModelBlueprint {
+    type Error = ModelLoadError;
+
+    fn try_from(data: ModelData) -> Result<Self, Self::Error> {
+        if !data.meta.is_supported() {
+            return Err(ModelLoadError::UnsupportedFormat {
+                version: data.meta.format_version,
+            });
+        }
         let elements: HashMap<String, ModelElement> = data
             .elements
             .iter()
             .map(|e| (e.uuid.clone(), e.clone()))
             .collect();
-        let group = data
+        let mut splits = Vec::new();
+        let (mut group, root): (Vec<BlueprintChildren>, Vec<BlueprintChildren>) = data
             .outliner
             .iter()
-            .map(|e| BlueprintChildren::from(e, &elements))
-            .collect();
+            .map(|e| BlueprintChildren::try_from(e, &elements, &mut splits))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .partition(|c| matches!(c, BlueprintChildren::Group(_)));
+        if !root.is_empty() {
+            let name = root_bone(&group);
+            let children = root
+                .into_iter()
+                .map(|c| c.split_rotation(&name, &mut splits))
+                .collect();
+            group.push(BlueprintChildren::Group(BlueprintGroup {
+                name,
+                origin: Float3::default(),
+                rotation: Float3::default(),
+                children,
+            }));
+        }
+        let textures = data
+            .textures
+            .iter()
+            .map(BlueprintTexture::try_from)
+            .collect::<Result<_, _>>()?;
 
-        ModelBlueprint {
-            name: data.name,
+        Ok(ModelBlueprint {
             scale: data.scale(),
+            name: data.name,
             resolution: data.resolution,
-            textures: data.textures.iter().map(|e| e.into()).collect(),
-            animations: data.animations.iter().map(|e| e.to),
-            group: group,
+            textures,
+            animations: data
+                .animations
+                .iter()
+                .map(|e| (e.name.clone(), BlueprintAnimation::from(&group, e)))
+                .collect(),
+            group,
+            splits,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use base64::Engine;
+    use base64::prelude::BASE64_STANDARD;
+    use image::ImageFormat;
+    use image::RgbaImage;
+
+    use crate::data::error::ModelLoadError;
+    use crate::data::fixture;
+    use crate::data::fixture::model_data;
+    use crate::data::raw::float3::Float3;
+    use crate::data::raw::model::FrameOrderType;
+    use crate::data::raw::model::ModelChildren;
+    use crate::data::raw::model::ModelTexture;
+
+    use super::BlueprintTexture;
+    use super::ModelBlueprint;
+    use super::ROOT_BONE;
+
+    fn model_texture(width: u32, height: u32, uv_height: u32) -> ModelTexture {
+        let mut png = Vec::new();
+        RgbaImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        ModelTexture {
+            name: "eyes.png".to_string(),
+            source: format!("data:image/png;base64,{}", BASE64_STANDARD.encode(png)),
+            width,
+            height,
+            uv_width: width,
+            uv_height,
+            frame_time: 2,
+            frame_order_type: FrameOrderType::BackAndForth,
+            frame_order: String::new(),
+            frame_interpolate: true,
         }
     }
+
+    #[test]
+    fn test_split_invalid_rotation() {
+        let mut model = model_data();
+        model.elements[0].rotation = Float3::new(10.0, 0.0, 30.0);
+        let uuid = model.elements[0].uuid.clone();
+        let blueprint = ModelBlueprint::try_from(model).unwrap();
+
+        assert_eq!(blueprint.splits.len(), 1);
+        let split = &blueprint.splits[0];
+        assert_eq!(split.uuid, uuid);
+        let bone = blueprint
+            .bones()
+            .into_iter()
+            .find(|b| b.name == split.bone)
+            .unwrap();
+        assert_eq!(bone.rotation.z, 30.0);
+        assert!(bone.elements().all(|e| e.rotation.z == 0.0));
+    }
+
+    #[test]
+    fn test_split_root_element() {
+        let mut model = model_data();
+        let mut element = model.elements[0].clone();
+        element.uuid = "root-element".to_string();
+        element.rotation = Float3::new(0.0, 45.0, 10.0);
+        model
+            .outliner
+            .push(ModelChildren::Element(element.uuid.clone()));
+        model.elements.push(element);
+        let blueprint = ModelBlueprint::try_from(model).unwrap();
+
+        assert_eq!(blueprint.splits.len(), 1);
+        let split = &blueprint.splits[0];
+        assert_eq!(split.uuid, "root-element");
+        let root = blueprint
+            .root_groups()
+            .find(|g| g.name.name() == ROOT_BONE)
+            .unwrap();
+        assert_eq!(root.elements().count(), 0);
+        assert!(root.groups().any(|g| g.name == split.bone));
+    }
+
+    #[test]
+    fn test_root_elements() {
+        let mut model = model_data();
+        let mut element = model.elements[0].clone();
+        element.uuid = "root-element".to_string();
+        model
+            .outliner
+            .push(ModelChildren::Element(element.uuid.clone()));
+        model.elements.push(element);
+        let blueprint = ModelBlueprint::try_from(model).unwrap();
+
+        let root = blueprint
+            .root_groups()
+            .find(|g| g.name.name() == ROOT_BONE)
+            .unwrap();
+        assert_eq!(root.elements().count(), 1);
+        assert_eq!(blueprint.root_groups().count(), 3);
+    }
+
+    #[test]
+    fn test_animated_texture() {
+        let texture = BlueprintTexture::try_from(&model_texture(16, 64, 16)).unwrap();
+        let animation = texture.animation.unwrap();
+        assert_eq!(animation.frame_count, 4);
+        assert_eq!(animation.frames, vec![0, 1, 2, 3, 2, 1]);
+        assert_eq!(animation.frame_time, 2);
+
+        let texture = BlueprintTexture::try_from(&model_texture(16, 16, 16)).unwrap();
+        assert!(texture.animation.is_none());
+
+        let error = BlueprintTexture::try_from(&model_texture(16, 40, 16))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            ModelLoadError::InvalidTextureAnimation {
+                frame_height: 16,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_molang() {
+        let mut json = fixture::json();
+        let point = &mut json["animations"][0]["animators"]["d8787749-7db8-7f91-4abb-369513b912a2"]
+            ["keyframes"][0]["data_points"][0];
+        point["x"] = "foo.bar + unknown * 2".into();
+        point["y"] = "math.nope(1) + q.is_on_ground".into();
+        let model = serde_json::from_value(json).unwrap();
+
+        let blueprint = ModelBlueprint::try_from(model).unwrap();
+        assert!(blueprint.animations.contains_key("animation.model.new"));
+    }
 }
@@ -1,31 +1,115 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::RangeBounds;
 use std::sync::Arc;
 use std::sync::LazyLock;
+use std::sync::OnceLock;
+use std::sync::RwLock;
 
+use pumpkin_util::math::vector3::Vector3;
+
+use crate::bone::BONE_TAG_REGISTRY;
+use crate::bone::BoneName;
 use crate::data::raw::model::ModelAnimation;
 
 use super::animation::AnimationType;
 
-pub struct RenderSource {}
+/// Effect of a script on the world or on the model playing it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptEffect {
+    Sound(String),
+    Particle(String),
+    /// Server command, run as the console
+    Command(String),
+    Hide(BoneName),
+    Show(BoneName),
+    /// Dye color of `bone`, or of every bone, `None` removes it
+    Tint {
+        bone: Option<BoneName>,
+        color: Option<i32>,
+    },
+}
+
+/// World state of a model instance handed to its scripts, collects the effects they play.
+pub struct RenderSource {
+    position: Vector3<f64>,
+    bones: HashMap<BoneName, Vector3<f64>>,
+    effects: Vec<(ScriptEffect, Vector3<f64>)>,
+}
+
+impl RenderSource {
+    /// `bones` holds the world position of the pivot of every bone.
+    pub fn new(position: Vector3<f64>, bones: HashMap<BoneName, Vector3<f64>>) -> Self {
+        Self {
+            position,
+            bones,
+            effects: Vec::new(),
+        }
+    }
+
+    pub fn position(&self) -> Vector3<f64> {
+        self.position
+    }
+
+    /// Position of `bone`, the model location for unknown bones.
+    pub fn bone_position(&self, bone: &BoneName) -> Vector3<f64> {
+        self.bones.get(bone).copied().unwrap_or(self.position)
+    }
+
+    /// Plays `effect` on `bone`, or on the model location.
+    /// The position is ignored by the effects acting on the model itself.
+    pub fn play(&mut self, effect: ScriptEffect, bone: Option<&BoneName>) {
+        let position = bone.map_or(self.position, |b| self.bone_position(b));
+        self.effects.push((effect, position));
+    }
+
+    pub fn effects(&self) -> &[(ScriptEffect, Vector3<f64>)] {
+        &self.effects
+    }
+
+    pub fn take_effects(&mut self) -> Vec<(ScriptEffect, Vector3<f64>)> {
+        std::mem::take(&mut self.effects)
+    }
+}
 
 #[derive(Clone)]
 pub struct AnimationScript {
-    script: Arc<dyn Fn(RenderSource) + Send + Sync>,
+    script: Arc<dyn Fn(&mut RenderSource) + Send + Sync>,
 }
 
 impl AnimationScript {
     pub fn new<F>(script: F) -> Self
     where
-        F: Fn(RenderSource) + Send + Sync + 'static,
+        F: Fn(&mut RenderSource) + Send + Sync + 'static,
     {
         Self {
             script: Arc::new(script),
         }
     }
 
+    /// Script playing `effect` at the current position of `bone`.
+    pub fn effect(effect: ScriptEffect, bone: Option<BoneName>) -> Self {
+        Self::new(move |source| source.play(effect.clone(), bone.as_ref()))
+    }
+
+    /// Runs `scripts` one after the other.
+    pub fn all(scripts: Vec<AnimationScript>) -> Self {
+        Self::new(move |source| {
+            for script in &scripts {
+                script.run(source);
+            }
+        })
+    }
+
+    pub fn run(&self, source: &mut RenderSource) {
+        (self.script)(source)
+    }
+
     pub fn time(&self, time: f32) -> TimeScript {
         TimeScript {
             time,
             script: self.clone(),
+            timeline: None,
         }
     }
 
@@ -39,9 +123,46 @@ impl AnimationScript {
 pub struct TimeScript {
     time: f32,
     script: AnimationScript,
+    /// Source of a timeline keyframe
+    timeline: Option<String>,
 }
 
 impl TimeScript {
+    /// Timeline keyframe, `source` is parsed by the `ScriptActionRegistry` the first time it runs
+    /// so the actions registered by other plugins after the models are loaded are found.
+    pub fn timeline(time: f32, source: impl Into<String>) -> Self {
+        let source = source.into();
+        let raw = source.clone();
+        let resolved = OnceLock::new();
+        let script = AnimationScript::new(move |render| {
+            resolved
+                .get_or_init(|| {
+                    let (script, errors) = get_script_registry().read().unwrap().parse(&raw);
+                    for error in errors {
+                        log::warn!("Timeline script: {error}");
+                    }
+                    script
+                })
+                .run(render)
+        });
+        Self {
+            timeline: Some(source),
+            ..script.time(time)
+        }
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.timeline.as_deref()
+    }
+
+    pub fn run(&self, source: &mut RenderSource) {
+        self.script.run(source)
+    }
+
     pub fn empty() -> Arc<TimeScript> {
         static EMPTY: LazyLock<Arc<TimeScript>> =
             LazyLock::new(|| Arc::new(AnimationScript::empty().time(0.0)));
@@ -56,16 +177,314 @@ pub struct BlueprintScript {
     scripts: Vec<Arc<TimeScript>>,
 }
 
-impl From<&ModelAnimation> for BlueprintScript {
-    fn from(animation: &ModelAnimation) -> Self {
+impl BlueprintScript {
+    /// Scripts of `animation` sorted by time, between an empty script at 0 and one at its length.
+    pub fn new(animation: &ModelAnimation, scripts: Vec<TimeScript>) -> Self {
+        let mut scripts: Vec<Arc<TimeScript>> = scripts.into_iter().map(Arc::new).collect();
+        scripts.sort_by(|a, b| a.time.total_cmp(&b.time));
+        scripts.insert(0, TimeScript::empty());
+        scripts.push(Arc::new(AnimationScript::empty().time(animation.length)));
         Self {
             name: animation.name.clone(),
-            typee: animation.looptype.clone(),
+            typee: animation.looptype,
             lenth: animation.length,
-            scripts: vec![
-                TimeScript::empty(),
-                Arc::new(AnimationScript::empty().time(animation.length)),
-            ],
+            scripts,
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn loop_type(&self) -> AnimationType {
+        self.typee
+    }
+
+    pub fn length(&self) -> f32 {
+        self.lenth
+    }
+
+    pub fn scripts(&self) -> &[Arc<TimeScript>] {
+        &self.scripts
+    }
+
+    /// Scripts whose time is in `range`, in time order.
+    pub fn between(&self, range: impl RangeBounds<f32>) -> impl Iterator<Item = &Arc<TimeScript>> {
+        self.scripts.iter().filter(move |s| range.contains(&s.time))
+    }
+}
+
+impl From<&ModelAnimation> for BlueprintScript {
+    fn from(animation: &ModelAnimation) -> Self {
+        Self::new(animation, Vec::new())
+    }
+}
+
+/// Builds the script of an `action:argument` line, the error describes an invalid argument.
+pub type ScriptAction = Arc<dyn Fn(&str) -> Result<AnimationScript, String> + Send + Sync>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptError {
+    MissingAction { line: String },
+    UnknownAction { action: String },
+    InvalidArgument { action: String, message: String },
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptError::MissingAction { line } => {
+                write!(f, "\"{line}\" is not an action:argument line")
+            }
+            ScriptError::UnknownAction { action } => write!(f, "unknown script action {action}"),
+            ScriptError::InvalidArgument { action, message } => {
+                write!(f, "invalid argument for {action}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Actions usable in the timeline keyframes, one `action:argument` per line.
+pub struct ScriptActionRegistry {
+    actions: HashMap<String, ScriptAction>,
+}
+
+impl ScriptActionRegistry {
+    pub fn new() -> Self {
+        Self {
+            actions: HashMap::new(),
+        }
+    }
+
+    /// Replaces the action with the same name, which is returned.
+    pub fn register<F>(&mut self, name: impl Into<String>, action: F) -> Option<ScriptAction>
+    where
+        F: Fn(&str) -> Result<AnimationScript, String> + Send + Sync + 'static,
+    {
+        self.actions
+            .insert(name.into().to_ascii_lowercase(), Arc::new(action))
+    }
+
+    pub fn unregister(&mut self, name: &str) -> Option<ScriptAction> {
+        self.actions.remove(&name.to_ascii_lowercase())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.actions.contains_key(&name.to_ascii_lowercase())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.actions.keys()
+    }
+
+    /// Script running every valid line of `source`, with the errors of the other lines.
+    /// Empty lines and lines starting with `#` or `//` are skipped.
+    pub fn parse(&self, source: &str) -> (AnimationScript, Vec<ScriptError>) {
+        let mut scripts = Vec::new();
+        let mut errors = Vec::new();
+        for line in source.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+            let Some((action, argument)) = line.split_once(':') else {
+                errors.push(ScriptError::MissingAction {
+                    line: line.to_string(),
+                });
+                continue;
+            };
+            let action = action.trim().to_ascii_lowercase();
+            let Some(handler) = self.actions.get(&action) else {
+                errors.push(ScriptError::UnknownAction { action });
+                continue;
+            };
+            match handler(argument.trim()) {
+                Ok(script) => scripts.push(script),
+                Err(message) => errors.push(ScriptError::InvalidArgument { action, message }),
+            }
+        }
+        (AnimationScript::all(scripts), errors)
+    }
+}
+
+impl Default for ScriptActionRegistry {
+    /// `sound:<name> [bone]`, `particle:<name> [bone]`, `command:<command>`, `tint:<rrggbb|reset> [bone]`,
+    /// `hide:<bone>` and `show:<bone>`.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        let effect = |effect: fn(String) -> ScriptEffect| {
+            move |argument: &str| -> Result<AnimationScript, String> {
+                let mut split = argument.split_whitespace();
+                let name = split.next().ok_or("missing name")?;
+                let bone = split.next().map(parse_bone);
+                Ok(AnimationScript::effect(effect(name.to_string()), bone))
+            }
+        };
+        let bone = |effect: fn(BoneName) -> ScriptEffect| {
+            move |argument: &str| -> Result<AnimationScript, String> {
+                if argument.is_empty() {
+                    return Err("missing bone".to_string());
+                }
+                let bone = parse_bone(argument);
+                Ok(AnimationScript::new(move |source| {
+                    source.play(effect(bone.clone()), None)
+                }))
+            }
+        };
+        registry.register("sound", effect(ScriptEffect::Sound));
+        registry.register("particle", effect(ScriptEffect::Particle));
+        registry.register("command", |argument: &str| {
+            let command = argument.strip_prefix('/').unwrap_or(argument).to_string();
+            if command.is_empty() {
+                return Err("missing command".to_string());
+            }
+            Ok(AnimationScript::new(move |source| {
+                source.play(ScriptEffect::Command(command.clone()), None)
+            }))
+        });
+        registry.register("tint", |argument: &str| {
+            let mut split = argument.split_whitespace();
+            let color = match split.next().ok_or("missing color")? {
+                "reset" => None,
+                color => Some(parse_color(color)?),
+            };
+            let bone = split.next().map(parse_bone);
+            Ok(AnimationScript::new(move |source| {
+                source.play(
+                    ScriptEffect::Tint {
+                        bone: bone.clone(),
+                        color,
+                    },
+                    None,
+                )
+            }))
+        });
+        registry.register("hide", bone(ScriptEffect::Hide));
+        registry.register("show", bone(ScriptEffect::Show));
+        registry
+    }
+}
+
+fn parse_bone(name: &str) -> BoneName {
+    BONE_TAG_REGISTRY.read().unwrap().parse(name)
+}
+
+/// `rrggbb` color, with an optional `#`.
+fn parse_color(color: &str) -> Result<i32, String> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if hex.len() != 6 {
+        return Err(format!("{color} is not a rrggbb color"));
+    }
+    i32::from_str_radix(hex, 16).map_err(|e| format!("{color}: {e}"))
+}
+
+pub static SCRIPT_ACTION_REGISTRY: LazyLock<Arc<RwLock<ScriptActionRegistry>>> =
+    LazyLock::new(|| Arc::new(RwLock::new(ScriptActionRegistry::default())));
+
+pub fn get_script_registry() -> Arc<RwLock<ScriptActionRegistry>> {
+    SCRIPT_ACTION_REGISTRY.clone()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use pumpkin_util::math::vector3::Vector3;
+
+    use crate::data::fixture::bone;
+
+    use super::AnimationScript;
+    use super::RenderSource;
+    use super::ScriptActionRegistry;
+    use super::ScriptEffect;
+    use super::ScriptError;
+    use super::TimeScript;
+    use super::get_script_registry;
+
+    fn effects(script: &AnimationScript) -> Vec<ScriptEffect> {
+        let mut source = RenderSource::new(Vector3::new(0.0, 0.0, 0.0), HashMap::new());
+        script.run(&mut source);
+        source.take_effects().into_iter().map(|(e, _)| e).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut registry = ScriptActionRegistry::default();
+        registry.register("glow", |argument: &str| {
+            let color = u32::from_str_radix(argument, 16).map_err(|e| e.to_string())?;
+            Ok(AnimationScript::new(move |source| {
+                source.play(ScriptEffect::Command(format!("glow {color}")), None)
+            }))
+        });
+        let (script, errors) = registry.parse(
+            "sound: entity.pig.ambient\n# commentaire\n\nCOMMAND:/say hi\nglow:ff\nglow:zz\nfly:up\nv.x = 1;",
+        );
+        assert_eq!(
+            effects(&script),
+            vec![
+                ScriptEffect::Sound("entity.pig.ambient".to_string()),
+                ScriptEffect::Command("say hi".to_string()),
+                ScriptEffect::Command("glow 255".to_string()),
+            ]
+        );
+        assert_eq!(errors.len(), 3);
+        assert!(
+            matches!(&errors[0], ScriptError::InvalidArgument { action, .. } if action == "glow")
+        );
+        assert_eq!(
+            errors[1],
+            ScriptError::UnknownAction {
+                action: "fly".to_string()
+            }
+        );
+        assert!(matches!(errors[2], ScriptError::MissingAction { .. }));
+
+        assert!(registry.contains("GLOW"));
+        assert!(registry.unregister("Glow").is_some());
+        assert!(!registry.contains("glow"));
+        assert!(registry.parse("hide:").1.len() == 1);
+    }
+
+    #[test]
+    fn test_tint() {
+        let registry = ScriptActionRegistry::default();
+        let (script, errors) = registry.parse("tint:#ff0000 bone2\ntint:reset\ntint:red");
+        assert_eq!(
+            effects(&script),
+            vec![
+                ScriptEffect::Tint {
+                    bone: Some(bone("bone2")),
+                    color: Some(0xff0000),
+                },
+                ScriptEffect::Tint {
+                    bone: None,
+                    color: None,
+                },
+            ]
+        );
+        assert!(
+            matches!(&errors[..], [ScriptError::InvalidArgument { action, .. }] if action == "tint")
+        );
+    }
+
+    #[test]
+    fn test_timeline_resolved_on_run() {
+        let script = TimeScript::timeline(0.5, "late_action:hi");
+        get_script_registry()
+            .write()
+            .unwrap()
+            .register("late_action", |argument: &str| {
+                let command = argument.to_string();
+                Ok(AnimationScript::new(move |source| {
+                    source.play(ScriptEffect::Command(command.clone()), None)
+                }))
+            });
+        let mut source = RenderSource::new(Vector3::new(0.0, 0.0, 0.0), HashMap::new());
+        script.run(&mut source);
+        assert_eq!(
+            source.effects()[0].0,
+            ScriptEffect::Command("hi".to_string())
+        );
+    }
 }
@@ -0,0 +1,86 @@
+use std::fmt::Display;
+
+/// Everything that can go wrong while turning a `.bbmodel` file into a `ModelBlueprint`.
+#[derive(Debug)]
+pub enum ModelLoadError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnsupportedFormat {
+        version: String,
+    },
+    UnknownElement {
+        uuid: String,
+    },
+    InvalidTextureUrl {
+        texture: String,
+    },
+    TextureDecode {
+        texture: String,
+        source: base64::DecodeError,
+    },
+    TextureImage {
+        texture: String,
+        source: image::ImageError,
+    },
+    InvalidTextureAnimation {
+        texture: String,
+        height: u32,
+        frame_height: u32,
+    },
+}
+
+impl Display for ModelLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelLoadError::Io(e) => write!(f, "failed to read model: {e}"),
+            ModelLoadError::Json(e) => write!(f, "invalid model json: {e}"),
+            ModelLoadError::UnsupportedFormat { version } => {
+                write!(f, "unsupported bbmodel format_version \"{version}\"")
+            }
+            ModelLoadError::UnknownElement { uuid } => {
+                write!(f, "outliner references unknown element {uuid}")
+            }
+            ModelLoadError::InvalidTextureUrl { texture } => {
+                write!(f, "texture {texture} has no base64 data url")
+            }
+            ModelLoadError::TextureDecode { texture, source } => {
+                write!(f, "texture {texture} is not valid base64: {source}")
+            }
+            ModelLoadError::TextureImage { texture, source } => {
+                write!(f, "texture {texture} is not a valid image: {source}")
+            }
+            ModelLoadError::InvalidTextureAnimation {
+                texture,
+                height,
+                frame_height,
+            } => write!(
+                f,
+                "texture {texture} is {height}px high, which is not a multiple of its {frame_height}px frames"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ModelLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModelLoadError::Io(e) => Some(e),
+            ModelLoadError::Json(e) => Some(e),
+            ModelLoadError::TextureDecode { source, .. } => Some(source),
+            ModelLoadError::TextureImage { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ModelLoadError {
+    fn from(value: std::io::Error) -> Self {
+        ModelLoadError::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for ModelLoadError {
+    fn from(value: serde_json::Error) -> Self {
+        ModelLoadError::Json(value)
+    }
+}
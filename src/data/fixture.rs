@@ -0,0 +1,55 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::bone::BoneName;
+use crate::bone::BoneTag;
+use crate::data::blueprint::BlueprintChildren;
+use crate::data::blueprint::ModelBlueprint;
+use crate::data::raw::model::ModelData;
+
+/// Model shared by the tests: bone2 > bone4 and bone > bone3, each with one element, one texture and one animation.
+pub const MODEL: &str = "test/test2.json";
+
+/// Raw json of `MODEL`, for tests editing it before deserializing.
+pub fn json() -> Value {
+    let file = File::open(MODEL).expect("failed to open file");
+    serde_json::from_reader(BufReader::new(file)).unwrap()
+}
+
+pub fn model_data() -> ModelData {
+    let file = File::open(MODEL).expect("failed to open file");
+    serde_json::from_reader(BufReader::new(file)).unwrap()
+}
+
+pub fn load() -> ModelBlueprint {
+    ModelBlueprint::try_from(model_data()).unwrap()
+}
+
+pub fn blueprint() -> Arc<ModelBlueprint> {
+    Arc::new(load())
+}
+
+/// Untagged bone name.
+pub fn bone(name: &str) -> BoneName {
+    BoneName::new(HashSet::new(), name.to_string(), name.to_string())
+}
+
+/// Gives `tag` to the groups named `raw_name`, keeping their name.
+pub fn tag(children: &mut [BlueprintChildren], raw_name: &str, tag: BoneTag) {
+    for child in children {
+        if let BlueprintChildren::Group(group) = child {
+            if group.name.raw_name() == raw_name {
+                group.name = BoneName::new(
+                    HashSet::from([tag.clone()]),
+                    raw_name.to_string(),
+                    raw_name.to_string(),
+                );
+            }
+            self::tag(&mut group.children, raw_name, tag.clone());
+        }
+    }
+}
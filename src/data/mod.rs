@@ -1,2 +1,6 @@
 pub mod blueprint;
+pub mod error;
+#[cfg(test)]
+pub mod fixture;
 pub mod raw;
+pub mod registry;
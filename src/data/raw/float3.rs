@@ -10,7 +10,7 @@ pub struct Float3 {
 }
 
 impl Float3 {
-    const CENTER: Float3 = Float3::new(8., 8., 8.);
+    pub const CENTER: Float3 = Float3::new(8., 8., 8.);
 
     pub fn to_vec3(&self) -> Vector3<f32> {
         Vector3::new(self.x, self.y, self.z)
@@ -24,7 +24,7 @@ impl Float3 {
         Self { x, y, z }
     }
 
-    pub async fn add(&self, other: &Self) -> Self {
+    pub fn add(&self, other: &Self) -> Self {
         Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
     }
 
@@ -32,19 +32,19 @@ impl Float3 {
         Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
     }
 
-    pub async fn mul(&self, other: &Self) -> Self {
+    pub fn mul(&self, other: &Self) -> Self {
         Self::new(self.x * other.x, self.y * other.y, self.z * other.z)
     }
 
-    pub async fn div(&self, other: &Self) -> Self {
+    pub fn div(&self, other: &Self) -> Self {
         Self::new(self.x / other.x, self.y / other.y, self.z / other.z)
     }
 
-    pub async fn invert_xz(&self) -> Self {
+    pub fn invert_xz(&self) -> Self {
         Self::new(-self.z, self.y, -self.x)
     }
 
-    pub async fn to_block_scale(&self) -> Self {
-        self.div(&Self::flat(16.0)).await
+    pub fn to_block_scale(&self) -> Self {
+        self.div(&Self::flat(16.0))
     }
 }
@@ -3,8 +3,8 @@ use serde::Serialize;
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Float4 {
-    dx: f32,
-    dz: f32,
-    tx: f32,
-    ty: f32,
+    pub dx: f32,
+    pub dz: f32,
+    pub tx: f32,
+    pub ty: f32,
 }
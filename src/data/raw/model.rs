@@ -2,8 +2,15 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::data::blueprint::animation::AnimationType;
-use crate::utils::default_interpolation;
-use crate::utils::VectorInterpolation;
+use crate::data::error::ModelLoadError;
+use crate::utils::interpolate::BezierInterpolation;
+use crate::utils::interpolate::CatmullRomInterpolation;
+use crate::utils::interpolate::LinearInterpolation;
+use crate::utils::interpolate::StepInterpolation;
+use crate::utils::interpolate::VectorInterpolation;
+use crate::utils::interpolate::default_interpolation;
+use crate::utils::molang::Molang;
+use crate::utils::molang::MolangContext;
 
 use super::float3::Float3;
 use super::float4::Float4;
@@ -17,35 +24,50 @@ use serde::Serialize;
 
 #[derive(Serialize, Deserialize, Default, Debug)]
 pub struct ModelResolution {
-    width: u32,
-    height: u32,
+    pub width: u32,
+    pub height: u32,
 }
 
-fn format_texture<'de, D>(deserializer: D) -> Result<String, D::Error>
+fn format_texture<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let value = u32::deserialize(deserializer)?;
-    Ok(format!("#{}", value))
+    let value = Option::<u32>::deserialize(deserializer)?;
+    Ok(value.map(|value| format!("#{}", value)))
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct ModelUV {
-    uv: Float4,
+    pub uv: Float4,
     #[serde(default)]
-    rotation: f32,
-    #[serde(deserialize_with = "format_texture")]
-    texture: String,
+    pub rotation: f32,
+    /// `None` when the face has no texture and should not be rendered
+    #[serde(default, deserialize_with = "format_texture")]
+    pub texture: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-struct ModelFace {
-    up: ModelUV,
-    down: ModelUV,
-    north: ModelUV,
-    south: ModelUV,
-    west: ModelUV,
-    east: ModelUV,
+pub struct ModelFace {
+    pub up: ModelUV,
+    pub down: ModelUV,
+    pub north: ModelUV,
+    pub south: ModelUV,
+    pub west: ModelUV,
+    pub east: ModelUV,
+}
+
+impl ModelFace {
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &ModelUV)> {
+        [
+            ("north", &self.north),
+            ("east", &self.east),
+            ("south", &self.south),
+            ("west", &self.west),
+            ("up", &self.up),
+            ("down", &self.down),
+        ]
+        .into_iter()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -60,10 +82,15 @@ pub struct ModelElement {
     pub rotation: Float3,
     pub origin: Float3,
     pub faces: ModelFace,
-    #[serde(default)]
+    /// Hidden in Blockbench, the bone starts hidden when none of its elements is visible
+    #[serde(default = "default_visibility")]
     pub visibility: bool,
 }
 
+fn default_visibility() -> bool {
+    true
+}
+
 impl ModelElement {
     pub fn max(&self) -> f32 {
         self.to.sub(&self.from).to_vec3().length()
@@ -78,6 +105,41 @@ pub struct ModelTexture {
     pub height: u32,
     pub uv_width: u32,
     pub uv_height: u32,
+    #[serde(default = "default_frame_time")]
+    pub frame_time: u32,
+    #[serde(default)]
+    pub frame_order_type: FrameOrderType,
+    /// Space separated frame indices, overrides `frame_order_type` when not empty
+    #[serde(default)]
+    pub frame_order: String,
+    #[serde(default)]
+    pub frame_interpolate: bool,
+}
+
+fn default_frame_time() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameOrderType {
+    #[default]
+    Loop,
+    Forwards,
+    Backwards,
+    BackAndForth,
+}
+
+impl FrameOrderType {
+    pub fn frames(&self, count: u32) -> Vec<u32> {
+        match self {
+            FrameOrderType::Loop | FrameOrderType::Forwards => (0..count).collect(),
+            FrameOrderType::Backwards => (0..count).rev().collect(),
+            FrameOrderType::BackAndForth => (0..count)
+                .chain((1..count.saturating_sub(1)).rev())
+                .collect(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -91,29 +153,53 @@ pub enum KeyFrameChannel {
     Particle,
 }
 
-fn f32_from_str<'de, D>(deserializer: D) -> Result<f32, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let s = String::deserialize(deserializer)?;
-    s.parse::<f32>().map_err(serde::de::Error::custom)
-}
-
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DataPoint {
-    #[serde(deserialize_with = "f32_from_str")]
-    x: f32,
-    #[serde(deserialize_with = "f32_from_str")]
-    y: f32,
-    #[serde(deserialize_with = "f32_from_str")]
-    z: f32,
+    // Absents des keyframes sound, particle et timeline
+    #[serde(default)]
+    x: Molang,
+    #[serde(default)]
+    y: Molang,
+    #[serde(default)]
+    z: Molang,
     #[serde(default)]
     script: String,
+    /// Sound or particle name of `sound` and `particle` keyframes
+    #[serde(default)]
+    effect: String,
+    /// Bone the particle is spawned on, the model location when empty
+    #[serde(default)]
+    locator: String,
 }
 
 impl DataPoint {
     pub fn to_vector(&self) -> Vector3<f32> {
-        Vector3::new(self.x, self.y, self.z)
+        self.evaluate(&MolangContext::default())
+    }
+
+    /// Whether every axis was folded to a constant at load.
+    pub fn is_constant(&self) -> bool {
+        self.x.is_constant() && self.y.is_constant() && self.z.is_constant()
+    }
+
+    pub fn evaluate(&self, context: &MolangContext) -> Vector3<f32> {
+        Vector3::new(
+            self.x.eval(context),
+            self.y.eval(context),
+            self.z.eval(context),
+        )
+    }
+
+    pub fn script(&self) -> &str {
+        &self.script
+    }
+
+    pub fn effect(&self) -> &str {
+        &self.effect
+    }
+
+    pub fn locator(&self) -> &str {
+        &self.locator
     }
 }
 
@@ -135,11 +221,26 @@ pub struct ModelKeyFrame {
 }
 
 impl ModelKeyFrame {
-    pub fn find_interpolation(&self) -> Arc<dyn VectorInterpolation + Sync + Send + 'static> {
-        if self.interpolation.is_none() {
-            return default_interpolation();
+    /// `convert` maps bezier handle values into the same space as the channel's data points.
+    pub fn find_interpolation(
+        &self,
+        convert: impl Fn(Vector3<f32>) -> Vector3<f32>,
+    ) -> Arc<dyn VectorInterpolation + Sync + Send + 'static> {
+        match self.interpolation.as_deref() {
+            Some("linear") | None => Arc::new(LinearInterpolation),
+            Some("catmullrom") => Arc::new(CatmullRomInterpolation),
+            Some("step") => Arc::new(StepInterpolation),
+            Some("bezier") => Arc::new(BezierInterpolation::new(
+                self.bezier_left_time.to_vec3(),
+                convert(self.bezier_left_value.to_vec3()),
+                self.bezier_right_time.to_vec3(),
+                convert(self.bezier_right_value.to_vec3()),
+            )),
+            Some(other) => {
+                log::warn!("Unknown keyframe interpolation {other}, falling back to linear");
+                default_interpolation()
+            }
         }
-        todo!()
     }
 }
 
@@ -166,15 +267,18 @@ impl Ord for ModelKeyFrame {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ModelAnimator {
     pub name: Option<String>,
+    /// `bone`, or `effect` for the animator holding the sound, particle and timeline keyframes
+    #[serde(default, rename = "type")]
+    pub kind: String,
     pub keyframes: Vec<ModelKeyFrame>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ModelAnimation {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, rename = "loop")]
     pub looptype: AnimationType,
-    #[serde(default)]
+    #[serde(default, rename = "override")]
     pub overriding: bool,
     pub uuid: String,
     pub length: f32,
@@ -185,25 +289,40 @@ pub struct ModelAnimation {
 pub struct ModelGroupe {
     pub name: String,
     pub origin: Float3,
+    #[serde(default)]
     pub rotation: Float3,
     pub uuid: String,
     pub children: Vec<ModelChildren>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct ModelUUID {
-    pub uuid: String,
-}
-
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 pub enum ModelChildren {
-    Element(ModelUUID),
+    /// Elements are referenced by their bare uuid in the outliner
+    Element(String),
     Group(ModelGroupe),
 }
 
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct ModelMeta {
+    pub format_version: String,
+}
+
+impl ModelMeta {
+    /// Only the Blockbench 4+ layout (outliner, keyframe data points) is understood.
+    pub fn is_supported(&self) -> bool {
+        self.format_version
+            .split('.')
+            .next()
+            .and_then(|major| major.parse::<u32>().ok())
+            .is_some_and(|major| major >= 4)
+    }
+}
+
 #[derive(Serialize, Deserialize, Default, Debug)]
 pub struct ModelData {
+    #[serde(default)]
+    pub meta: ModelMeta,
     pub name: String,
     pub resolution: ModelResolution,
     pub elements: Vec<ModelElement>,
@@ -213,6 +332,10 @@ pub struct ModelData {
 }
 
 impl ModelData {
+    pub fn from_reader(reader: impl std::io::Read) -> Result<Self, ModelLoadError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
     pub fn scale(&self) -> f32 {
         return self
             .elements
@@ -229,6 +352,7 @@ mod test {
     use std::io::BufReader;
 
     use super::ModelData;
+    use super::ModelMeta;
 
     #[test]
     fn test_deserialize() {
@@ -246,4 +370,21 @@ mod test {
             .unwrap();
         println!("{:?}", model);
     }
+
+    #[test]
+    fn test_format_version() {
+        let meta = |v: &str| ModelMeta {
+            format_version: v.to_string(),
+        };
+        assert!(meta("4.10").is_supported());
+        assert!(meta("5.0").is_supported());
+        assert!(!meta("3.6").is_supported());
+        assert!(!meta("").is_supported());
+    }
+
+    #[test]
+    fn test_bad_json() {
+        let error = ModelData::from_reader("{ \"name\": ".as_bytes()).unwrap_err();
+        assert!(matches!(error, crate::data::error::ModelLoadError::Json(_)));
+    }
 }
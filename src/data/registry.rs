@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::RwLock;
+use std::time::Duration;
+use std::time::Instant;
+
+use super::blueprint::ModelBlueprint;
+use super::error::ModelLoadError;
+use super::raw::model::ModelData;
+
+pub const MODEL_EXTENSION: &str = "bbmodel";
+
+#[derive(Default)]
+pub struct ModelRegistry {
+    models: HashMap<String, Arc<ModelBlueprint>>,
+    /// File each model was read from, to parse it again
+    sources: HashMap<String, PathBuf>,
+}
+
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub loaded: usize,
+    pub failed: Vec<(PathBuf, ModelLoadError)>,
+    pub elapsed: Duration,
+}
+
+impl ModelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, name: impl Into<String>, model: ModelBlueprint) {
+        let name = name.into();
+        self.sources.remove(&name);
+        self.models.insert(name, Arc::new(model));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<ModelBlueprint>> {
+        self.models.get(name).cloned()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.models.keys()
+    }
+
+    pub fn models(&self) -> impl Iterator<Item = &Arc<ModelBlueprint>> {
+        self.models.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.models.clear();
+        self.sources.clear();
+    }
+
+    /// Loads every `.bbmodel` under `directory`, keyed by path relative to it without extension (`mobs/wolf`).
+    /// A broken model or an unreadable directory is reported in the returned `LoadReport` and does not stop the others.
+    pub fn load_directory(&mut self, directory: &Path) -> LoadReport {
+        let start = Instant::now();
+        let mut report = LoadReport::default();
+        let mut files = Vec::new();
+        collect_models(directory, &mut files, &mut report.failed);
+        for path in files {
+            match load_model(&path) {
+                Ok(mut model) => {
+                    // Le nom du modèle suit sa clé, unique dans le registre et donc dans le pack
+                    let name = model_name(directory, &path);
+                    model.name = name.clone();
+                    self.register(name.clone(), model);
+                    self.sources.insert(name, path);
+                    report.loaded += 1;
+                }
+                Err(e) => report.failed.push((path, e)),
+            }
+        }
+        report.elapsed = start.elapsed();
+        report
+    }
+
+    /// Reads again every model loaded from a file, so their bone names get the bone tags registered since.
+    /// A model failing to load keeps its previous version, the trackers already spawned keep theirs.
+    pub fn reparse(&mut self) -> LoadReport {
+        let start = Instant::now();
+        let mut report = LoadReport::default();
+        for (name, path) in &self.sources {
+            match load_model(path) {
+                Ok(mut model) => {
+                    model.name = name.clone();
+                    self.models.insert(name.clone(), Arc::new(model));
+                    report.loaded += 1;
+                }
+                Err(e) => report.failed.push((path.clone(), e)),
+            }
+        }
+        report.elapsed = start.elapsed();
+        report
+    }
+}
+
+/// Name of the model at `path`, its path relative to `directory` with `/` separators and without extension.
+fn model_name(directory: &Path, path: &Path) -> String {
+    let relative = path
+        .strip_prefix(directory)
+        .unwrap_or(path)
+        .with_extension("");
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn collect_models(
+    directory: &Path,
+    files: &mut Vec<PathBuf>,
+    failed: &mut Vec<(PathBuf, ModelLoadError)>,
+) {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            failed.push((directory.to_path_buf(), e.into()));
+            return;
+        }
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(entry) => paths.push(entry.path()),
+            Err(e) => failed.push((directory.to_path_buf(), e.into())),
+        }
+    }
+    // Ordre stable d'un chargement à l'autre
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            collect_models(&path, files, failed);
+        } else if path.extension().is_some_and(|e| e == MODEL_EXTENSION) {
+            files.push(path);
+        }
+    }
+}
+
+pub fn load_model(path: &Path) -> Result<ModelBlueprint, ModelLoadError> {
+    let data = ModelData::from_reader(BufReader::new(File::open(path)?))?;
+    ModelBlueprint::try_from(data)
+}
+
+// Registry global des modèles chargés
+pub static MODEL_REGISTRY: LazyLock<Arc<RwLock<ModelRegistry>>> =
+    LazyLock::new(|| Arc::new(RwLock::new(ModelRegistry::new())));
+
+pub fn get_model_registry() -> Arc<RwLock<ModelRegistry>> {
+    MODEL_REGISTRY.clone()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::sync::Arc;
+
+    use super::ModelRegistry;
+
+    #[test]
+    fn test_load_directory() {
+        let directory = std::env::temp_dir().join("modelblock_test_load_directory");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("nested")).unwrap();
+        fs::copy("test/test2.json", directory.join("test2.bbmodel")).unwrap();
+        fs::copy("test/test2.json", directory.join("nested/test2.bbmodel")).unwrap();
+        fs::write(directory.join("broken.bbmodel"), "{").unwrap();
+        fs::write(directory.join("readme.txt"), "ignored").unwrap();
+
+        let mut registry = ModelRegistry::new();
+        let report = registry.load_directory(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(report.loaded, 2);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].0.ends_with("broken.bbmodel"));
+        // Même nom de fichier dans deux dossiers
+        assert!(registry.get("test2").is_some());
+        assert_eq!(registry.get("nested/test2").unwrap().name, "nested/test2");
+    }
+
+    #[test]
+    fn test_reparse() {
+        let directory = std::env::temp_dir().join("modelblock_test_reparse");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        fs::copy("test/test2.json", directory.join("test2.bbmodel")).unwrap();
+
+        let mut registry = ModelRegistry::new();
+        registry.load_directory(&directory);
+        let loaded = registry.get("test2").unwrap();
+        let report = registry.reparse();
+        assert_eq!(report.loaded, 1);
+        assert!(!Arc::ptr_eq(&loaded, &registry.get("test2").unwrap()));
+
+        // Un modèle cassé garde sa version précédente
+        fs::write(directory.join("test2.bbmodel"), "{").unwrap();
+        let report = registry.reparse();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(registry.get("test2").unwrap().name, "test2");
+    }
+
+    #[test]
+    fn test_missing_directory() {
+        let directory = std::env::temp_dir().join("modelblock_test_missing_directory");
+        let _ = fs::remove_dir_all(&directory);
+
+        let report = ModelRegistry::new().load_directory(&directory);
+        assert_eq!(report.loaded, 0);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, directory);
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+use std::path::Path;
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -11,10 +12,26 @@ use pumpkin::{
 use pumpkin_api_macros::with_runtime;
 use pumpkin_api_macros::{plugin_impl, plugin_method};
 use pumpkin_util::text::{color::NamedColor, TextComponent};
+use config::CONFIG_FILE;
+use config::PluginConfig;
+use data::registry::get_model_registry;
+use pack::AtlasMode;
+use pack::ResourcePack;
+use render::pumpkin::TrackerInputHandler;
+use render::pumpkin::TrackerInteractHandler;
+use render::pumpkin::TrackerJoinHandler;
+use render::pumpkin::TrackerLeaveHandler;
+use render::pumpkin::start_ticking;
 mod bone;
+mod config;
 mod data;
+mod pack;
+mod render;
 mod utils;
 
+const MODELS_FOLDER: &str = "models";
+const RESOURCE_PACK_FILE: &str = "resource_pack.zip";
+
 struct MyJoinHandler;
 
 #[with_runtime(global)]
@@ -30,7 +47,77 @@ impl EventHandler<PlayerJoinEvent> for MyJoinHandler {
 #[plugin_method]
 async fn on_load(&mut self, server: Arc<Context>) -> Result<(), String> {
     pumpkin::init_log!();
-    log::info!("Hello, Pumpkin");
+    let config_path = Path::new(&server.get_data_folder()).join(CONFIG_FILE);
+    let config = PluginConfig::load(&config_path).unwrap_or_else(|e| {
+        log::warn!("Using the default config, {}: {}", config_path.display(), e);
+        PluginConfig::default()
+    });
+    bone::initialize_bone_tags(&config.bone_tags);
+    let models = Path::new(&server.get_data_folder()).join(MODELS_FOLDER);
+    if let Err(e) = std::fs::create_dir_all(&models) {
+        log::error!("Failed to create {}: {}", models.display(), e);
+        return Ok(());
+    }
+    let registry = get_model_registry();
+    let report = registry.write().unwrap().load_directory(&models);
+    for (path, error) in &report.failed {
+        log::warn!("Skipping model {}: {}", path.display(), error);
+    }
+    log::info!(
+        "Loaded {} models ({} failed) in {:?}",
+        report.loaded,
+        report.failed.len(),
+        report.elapsed
+    );
+
+    let mut pack = ResourcePack::with_atlas("ModelBlock generated models", config.pack.atlas);
+    pack.set_max_atlas_size(config.pack.max_atlas_size);
+    {
+        let registry = registry.read().unwrap();
+        let models = registry.models().map(|m| m.as_ref()).collect::<Vec<_>>();
+        for model in &models {
+            for split in &model.splits {
+                log::info!(
+                    "Moved element {} of {} into bone {} for its rotation {:?}",
+                    split.element,
+                    model.name,
+                    split.bone.name(),
+                    split.rotation
+                );
+            }
+        }
+        if config.pack.atlas == AtlasMode::Global {
+            if let Err(e) = pack.add_models(&models) {
+                log::warn!("Failed to add the models to the resource pack: {}", e);
+            }
+        } else {
+            for model in models {
+                if let Err(e) = pack.add_model(model) {
+                    log::warn!("Failed to add {} to the resource pack: {}", model.name, e);
+                }
+            }
+        }
+    }
+    pack.add_skin_parts();
+    let pack_path = Path::new(&server.get_data_folder()).join(RESOURCE_PACK_FILE);
+    match pack.write_zip(&pack_path) {
+        Ok(()) => log::info!("Resource pack written to {}", pack_path.display()),
+        Err(e) => log::error!("Failed to write resource pack: {}", e),
+    }
+
+    server
+        .register_event(Arc::new(TrackerJoinHandler), EventPriority::Lowest, false)
+        .await;
+    server
+        .register_event(Arc::new(TrackerLeaveHandler), EventPriority::Lowest, false)
+        .await;
+    server
+        .register_event(Arc::new(TrackerInteractHandler), EventPriority::Lowest, false)
+        .await;
+    server
+        .register_event(Arc::new(TrackerInputHandler), EventPriority::Lowest, false)
+        .await;
+    start_ticking(server.server.clone());
     Ok(())
 }
 
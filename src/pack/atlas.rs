@@ -0,0 +1,107 @@
+use image::RgbaImage;
+use image::imageops;
+
+/// Where a texture was placed inside an `Atlas`, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtlasEntry {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct Atlas {
+    pub image: RgbaImage,
+    /// One entry per packed image, in the same order as the input.
+    pub entries: Vec<AtlasEntry>,
+}
+
+impl Atlas {
+    /// Packs `images` on shelves sorted by height into a power of two sized image.
+    pub fn pack(images: &[&RgbaImage]) -> Self {
+        let area: u64 = images
+            .iter()
+            .map(|i| i.width() as u64 * i.height() as u64)
+            .sum();
+        let widest = images.iter().map(|i| i.width()).max().unwrap_or(1);
+        let width = ((area as f64).sqrt().ceil() as u32)
+            .max(widest)
+            .max(1)
+            .next_power_of_two();
+
+        let mut order = (0..images.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| std::cmp::Reverse(images[i].height()));
+
+        let mut entries = vec![
+            AtlasEntry {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+            };
+            images.len()
+        ];
+        let (mut x, mut y, mut shelf) = (0, 0, 0);
+        for i in order {
+            let image = images[i];
+            if x + image.width() > width {
+                x = 0;
+                y += shelf;
+                shelf = 0;
+            }
+            entries[i] = AtlasEntry {
+                x,
+                y,
+                width: image.width(),
+                height: image.height(),
+            };
+            x += image.width();
+            shelf = shelf.max(image.height());
+        }
+        let height = (y + shelf).max(1).next_power_of_two();
+
+        let mut atlas = RgbaImage::new(width, height);
+        for (image, entry) in images.iter().zip(&entries) {
+            imageops::replace(&mut atlas, *image, entry.x as i64, entry.y as i64);
+        }
+        Self {
+            image: atlas,
+            entries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::Rgba;
+    use image::RgbaImage;
+
+    use super::Atlas;
+
+    #[test]
+    fn test_pack_without_overlap() {
+        let images = [
+            RgbaImage::from_pixel(16, 16, Rgba([255, 0, 0, 255])),
+            RgbaImage::from_pixel(64, 32, Rgba([0, 255, 0, 255])),
+            RgbaImage::from_pixel(8, 48, Rgba([0, 0, 255, 255])),
+        ];
+        let atlas = Atlas::pack(&images.iter().collect::<Vec<_>>());
+
+        for (a, ea) in atlas.entries.iter().enumerate() {
+            assert!(ea.x + ea.width <= atlas.image.width());
+            assert!(ea.y + ea.height <= atlas.image.height());
+            for eb in &atlas.entries[a + 1..] {
+                let overlap = ea.x < eb.x + eb.width
+                    && eb.x < ea.x + ea.width
+                    && ea.y < eb.y + eb.height
+                    && eb.y < ea.y + ea.height;
+                assert!(!overlap);
+            }
+        }
+        let green = atlas.entries[1];
+        assert_eq!(
+            atlas.image.get_pixel(green.x, green.y),
+            &Rgba([0, 255, 0, 255])
+        );
+    }
+}
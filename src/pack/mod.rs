@@ -0,0 +1,878 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::Cursor;
+use std::io::Write;
+use std::path::Path;
+
+use image::ImageFormat;
+use image::RgbaImage;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use serde_json::json;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::bone::BoneName;
+use crate::bone::skin::SkinPart;
+use crate::data::blueprint::BlueprintGroup;
+use crate::data::blueprint::BlueprintTexture;
+use crate::data::blueprint::ModelBlueprint;
+use crate::data::raw::float3::Float3;
+use crate::data::raw::model::ModelElement;
+use crate::utils::math;
+
+use self::atlas::Atlas;
+use self::atlas::AtlasEntry;
+
+pub mod atlas;
+
+pub const NAMESPACE: &str = "modelblock";
+/// Resource pack format of Minecraft 1.21.4, the first version with `items/` definitions.
+pub const PACK_FORMAT: u32 = 46;
+/// Minecraft rejects element coordinates outside of [-16, 32], i.e. 24 units around the center.
+const MAX_ELEMENT_OFFSET: f32 = 24.0;
+/// Largest atlas size most graphics cards load, in pixels.
+pub const DEFAULT_MAX_ATLAS_SIZE: u32 = 4096;
+const ATLAS_TEXTURE: &str = "atlas";
+const ATLAS_VARIABLE: &str = "#atlas";
+
+/// A bone written to the pack, with what the renderer needs to display it.
+#[derive(Clone, Debug)]
+pub struct PackedBone {
+    pub bone: BoneName,
+    /// Item model id to put in the `item_model` component of the display item.
+    pub item_model: String,
+    /// Elements are shrunk to fit the model bounds, the display must scale them back by this.
+    pub scale: f32,
+}
+
+#[derive(Debug)]
+pub enum PackError {
+    Image(image::ImageError),
+    /// `model` is written to the same folder as a model already in the pack
+    NameClash {
+        model: String,
+        path: String,
+    },
+}
+
+impl Display for PackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackError::Image(e) => write!(f, "failed to encode texture: {e}"),
+            PackError::NameClash { model, path } => {
+                write!(
+                    f,
+                    "model {model} clashes with another model written to {path}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PackError::Image(e) => Some(e),
+            PackError::NameClash { .. } => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for PackError {
+    fn from(value: image::ImageError) -> Self {
+        PackError::Image(value)
+    }
+}
+
+/// How model textures are laid out in the generated pack.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AtlasMode {
+    /// Every texture is written as its own png
+    #[default]
+    Disabled,
+    /// The textures of each model are packed into one atlas
+    PerModel,
+    /// Every model given to a single `add_models` call shares one atlas
+    Global,
+}
+
+struct AtlasLayout {
+    /// `None` for textures kept out of the atlas
+    entries: Vec<Option<AtlasEntry>>,
+    width: u32,
+    height: u32,
+}
+
+/// Texture variables of a model json and where each source texture ended up.
+struct TextureLayout {
+    textures: serde_json::Map<String, Value>,
+    atlas: Option<AtlasLayout>,
+}
+
+pub struct ResourcePack {
+    files: BTreeMap<String, Vec<u8>>,
+    bones: HashMap<String, Vec<PackedBone>>,
+    /// Folder of every added model, to detect the names sanitizing to the same folder
+    folders: HashSet<String>,
+    atlas: AtlasMode,
+    max_atlas_size: u32,
+    atlas_count: usize,
+}
+
+impl ResourcePack {
+    pub fn new(description: &str) -> Self {
+        Self::with_atlas(description, AtlasMode::Disabled)
+    }
+
+    pub fn with_atlas(description: &str, atlas: AtlasMode) -> Self {
+        let mut pack = Self {
+            files: BTreeMap::new(),
+            bones: HashMap::new(),
+            folders: HashSet::new(),
+            atlas,
+            max_atlas_size: DEFAULT_MAX_ATLAS_SIZE,
+            atlas_count: 0,
+        };
+        pack.add_json(
+            "pack.mcmeta".to_string(),
+            &json!({
+                "pack": {
+                    "pack_format": PACK_FORMAT,
+                    "description": description,
+                }
+            }),
+        );
+        pack
+    }
+
+    /// Atlases wider or higher than `size` are not written, their textures are written separately instead.
+    pub fn set_max_atlas_size(&mut self, size: u32) {
+        self.max_atlas_size = size.max(1);
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (&String, &Vec<u8>)> {
+        self.files.iter()
+    }
+
+    pub fn file(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path).map(|f| f.as_slice())
+    }
+
+    /// Bones of `model` that got an item model, empty if the model was never added.
+    pub fn bones(&self, model: &str) -> &[PackedBone] {
+        self.bones
+            .get(model)
+            .map(|b| b.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn add_json(&mut self, path: String, value: &Value) {
+        self.files.insert(
+            path,
+            serde_json::to_vec_pretty(value).expect("json values always serialize"),
+        );
+    }
+
+    pub fn add_model(&mut self, model: &ModelBlueprint) -> Result<(), PackError> {
+        self.add_models(&[model])
+    }
+
+    /// Adds `models` to the pack. In `AtlasMode::Global` they all share a single atlas.
+    /// Nothing is added when one of them would overwrite the files of another model.
+    pub fn add_models(&mut self, models: &[&ModelBlueprint]) -> Result<(), PackError> {
+        let mut folders = HashSet::new();
+        for model in models {
+            let path = model_folder(model);
+            if self.folders.contains(&path) || !folders.insert(path.clone()) {
+                return Err(PackError::NameClash {
+                    model: model.name.clone(),
+                    path,
+                });
+            }
+        }
+        self.folders.extend(folders);
+        match self.atlas {
+            AtlasMode::Disabled => {
+                for model in models {
+                    let layout = self.separate_textures(model)?;
+                    self.add_bones(model, &layout);
+                }
+            }
+            AtlasMode::PerModel => {
+                for model in models {
+                    let path = format!("{}/atlas", model_folder(model));
+                    match self.atlas_textures(&path, &[model])? {
+                        Some(mut layouts) => self.add_bones(model, &layouts.remove(0)),
+                        None => {
+                            let layout = self.separate_textures(model)?;
+                            self.add_bones(model, &layout);
+                        }
+                    }
+                }
+            }
+            AtlasMode::Global => {
+                let path = format!("atlas_{}", self.atlas_count);
+                match self.atlas_textures(&path, models)? {
+                    Some(layouts) => {
+                        self.atlas_count += 1;
+                        for (model, layout) in models.iter().zip(layouts) {
+                            self.add_bones(model, &layout);
+                        }
+                    }
+                    None => {
+                        for model in models {
+                            let layout = self.separate_textures(model)?;
+                            self.add_bones(model, &layout);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_png(&mut self, path: &str, image: &RgbaImage) -> Result<String, image::ImageError> {
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        self.files
+            .insert(format!("assets/{NAMESPACE}/textures/item/{path}.png"), png);
+        Ok(format!("{NAMESPACE}:item/{path}"))
+    }
+
+    /// Writes a model texture on its own, with its `.png.mcmeta` when it is animated.
+    /// A texture whose sanitized name is already taken in the model folder gets a `_2`, `_3`... suffix.
+    fn add_texture(
+        &mut self,
+        model: &ModelBlueprint,
+        texture: &BlueprintTexture,
+    ) -> Result<String, image::ImageError> {
+        let name = format!(
+            "{}/{}",
+            model_folder(model),
+            sanitize(texture.name.trim_end_matches(".png"))
+        );
+        let mut path = name.clone();
+        let mut suffix = 1;
+        while self
+            .files
+            .contains_key(&format!("assets/{NAMESPACE}/textures/item/{path}.png"))
+        {
+            suffix += 1;
+            path = format!("{name}_{suffix}");
+        }
+        let id = self.add_png(&path, &texture.image)?;
+        if let Some(animation) = &texture.animation {
+            // Sans taille explicite le client découpe des frames carrées
+            let (width, height) = texture.image.dimensions();
+            self.add_json(
+                format!("assets/{NAMESPACE}/textures/item/{path}.png.mcmeta"),
+                &json!({
+                    "animation": {
+                        "frametime": animation.frame_time,
+                        "interpolate": animation.interpolate,
+                        "width": width,
+                        "height": height / animation.frame_count.max(1),
+                        "frames": animation.frames,
+                    }
+                }),
+            );
+        }
+        Ok(id)
+    }
+
+    fn separate_textures(
+        &mut self,
+        model: &ModelBlueprint,
+    ) -> Result<TextureLayout, image::ImageError> {
+        let mut textures = serde_json::Map::new();
+        for (index, texture) in model.textures.iter().enumerate() {
+            let id = self.add_texture(model, texture)?;
+            textures.insert(index.to_string(), json!(id));
+        }
+        Ok(TextureLayout {
+            textures,
+            atlas: None,
+        })
+    }
+
+    /// Packs every texture of `models` into one image, returning a layout per model.
+    /// Animated textures stay separate since Minecraft animates a whole texture file.
+    /// Nothing is written and `None` is returned when the atlas would exceed `max_atlas_size`,
+    /// and no atlas is written when every texture is animated.
+    fn atlas_textures(
+        &mut self,
+        path: &str,
+        models: &[&ModelBlueprint],
+    ) -> Result<Option<Vec<TextureLayout>>, image::ImageError> {
+        let images = models
+            .iter()
+            .flat_map(|m| m.textures.iter())
+            .filter(|t| t.animation.is_none())
+            .map(|t| &t.image)
+            .collect::<Vec<_>>();
+        let atlas = Atlas::pack(&images);
+        let (width, height) = atlas.image.dimensions();
+        if width > self.max_atlas_size || height > self.max_atlas_size {
+            log::warn!(
+                "Atlas {path} would be {width}x{height}, over the {} limit, writing its textures separately",
+                self.max_atlas_size
+            );
+            return Ok(None);
+        }
+        let id = match images.is_empty() {
+            true => None,
+            false => Some(self.add_png(path, &atlas.image)?),
+        };
+        let mut entries = atlas.entries.into_iter();
+        let mut layouts = Vec::with_capacity(models.len());
+        for model in models {
+            let mut textures = serde_json::Map::new();
+            if let Some(id) = &id {
+                textures.insert(ATLAS_TEXTURE.to_string(), json!(id));
+            }
+            let mut model_entries = Vec::with_capacity(model.textures.len());
+            for (index, texture) in model.textures.iter().enumerate() {
+                if texture.animation.is_some() {
+                    let id = self.add_texture(model, texture)?;
+                    textures.insert(index.to_string(), json!(id));
+                    model_entries.push(None);
+                } else {
+                    model_entries.push(entries.next());
+                }
+            }
+            layouts.push(TextureLayout {
+                textures,
+                atlas: Some(AtlasLayout {
+                    entries: model_entries,
+                    width,
+                    height,
+                }),
+            });
+        }
+        Ok(Some(layouts))
+    }
+
+    fn add_bones(&mut self, model: &ModelBlueprint, layout: &TextureLayout) {
+        let mut packed = Vec::new();
+        for (bone, path) in model.bones().into_iter().zip(bone_paths(model)) {
+            let Some(packed_bone) = packed(bone, &path) else {
+                continue;
+            };
+            let scale = bone_scale(bone);
+            let json_elements = bone
+                .shown_elements()
+                .filter_map(|e| Self::element(model, bone, e, scale, layout))
+                .collect::<Vec<_>>();
+            self.add_json(
+                format!("assets/{NAMESPACE}/models/item/{path}.json"),
+                &json!({
+                    "textures": layout.textures,
+                    "elements": json_elements,
+                }),
+            );
+            self.add_json(
+                format!("assets/{NAMESPACE}/items/{path}.json"),
+                &json!({
+                    "model": {
+                        "type": "minecraft:model",
+                        "model": format!("{NAMESPACE}:item/{path}"),
+                        "tints": [{ "type": "minecraft:dye", "default": -1 }],
+                    }
+                }),
+            );
+            packed.push(packed_bone);
+        }
+        self.bones.insert(model.name.clone(), packed);
+    }
+
+    fn element(
+        model: &ModelBlueprint,
+        bone: &BlueprintGroup,
+        element: &ModelElement,
+        scale: f32,
+        layout: &TextureLayout,
+    ) -> Option<Value> {
+        if !math::check_valid_degree(&element.rotation) {
+            log::warn!(
+                "Element {} of {} has a rotation Minecraft cannot display, skipping it",
+                element.name,
+                model.name
+            );
+            return None;
+        }
+        // Décalage au pivot en blocs, ramené aux unités du modèle d'objet
+        let local = |v: &Float3| {
+            v.sub(&bone.origin)
+                .to_block_scale()
+                .mul(&Float3::flat(scale * math::MODEL_TO_BLOCK_MULTIPLIER))
+                .add(&Float3::CENTER)
+        };
+        let inflate = Float3::flat(element.inflate);
+        let from = local(&element.from.sub(&inflate));
+        let to = local(&element.to.add(&inflate));
+        let mut json = json!({
+            "name": element.name,
+            "from": [from.x, from.y, from.z],
+            "to": [to.x, to.y, to.z],
+            "faces": Self::faces(model, element, layout),
+        });
+        if let Some((axis, angle)) = [
+            ("x", element.rotation.x),
+            ("y", element.rotation.y),
+            ("z", element.rotation.z),
+        ]
+        .into_iter()
+        .find(|(_, angle)| *angle != 0.0)
+        {
+            let origin = local(&element.origin);
+            json["rotation"] = json!({
+                "angle": angle,
+                "axis": axis,
+                "origin": [origin.x, origin.y, origin.z],
+            });
+        }
+        Some(json)
+    }
+
+    fn faces(model: &ModelBlueprint, element: &ModelElement, layout: &TextureLayout) -> Value {
+        let mut faces = serde_json::Map::new();
+        for (direction, face) in element.faces.iter() {
+            let Some(texture) = &face.texture else {
+                continue;
+            };
+            let index = texture.trim_start_matches('#').parse::<usize>().ok();
+            let source = index.and_then(|i| model.textures.get(i));
+            // Blockbench uvs are expressed in the texture's uv size, which may differ
+            // from both the model resolution and the real image size
+            let (uv_width, uv_height) = source
+                .map(|t| (t.uv_width as f32, t.uv_height as f32))
+                .filter(|(w, h)| *w > 0.0 && *h > 0.0)
+                .unwrap_or((
+                    model.resolution.width as f32,
+                    model.resolution.height as f32,
+                ));
+            let atlas =
+                layout
+                    .atlas
+                    .as_ref()
+                    .zip(index.zip(source))
+                    .and_then(|(atlas, (i, source))| {
+                        atlas
+                            .entries
+                            .get(i)
+                            .copied()
+                            .flatten()
+                            .map(|entry| (atlas, entry, source))
+                    });
+            let uv = [face.uv.dx, face.uv.dz, face.uv.tx, face.uv.ty];
+            let (texture, uv) = match atlas {
+                Some((atlas, entry, source)) => {
+                    // Back to image pixels, then offset into the atlas
+                    let (image_width, image_height) = source.image.dimensions();
+                    let u = |u: f32| {
+                        (entry.x as f32 + u * image_width as f32 / uv_width) * 16.0
+                            / atlas.width as f32
+                    };
+                    let v = |v: f32| {
+                        (entry.y as f32 + v * image_height as f32 / uv_height) * 16.0
+                            / atlas.height as f32
+                    };
+                    (ATLAS_VARIABLE, [u(uv[0]), v(uv[1]), u(uv[2]), v(uv[3])])
+                }
+                None => {
+                    let u = |u: f32| u * 16.0 / uv_width;
+                    let v = |v: f32| v * 16.0 / uv_height;
+                    (texture.as_str(), [u(uv[0]), v(uv[1]), u(uv[2]), v(uv[3])])
+                }
+            };
+            // Teinte donnée par le composant dyed_color de l'objet
+            let mut json = json!({
+                "uv": uv,
+                "texture": texture,
+                "tintindex": 0,
+            });
+            if face.rotation != 0.0 {
+                json["rotation"] = json!(face.rotation);
+            }
+            faces.insert(direction.to_string(), json);
+        }
+        Value::Object(faces)
+    }
+
+    /// Item models of the player part bones, see `skin_item_model`.
+    /// Every part is a player head, the limbs are remapped to their skin UVs by the entity shader of the pack.
+    pub fn add_skin_parts(&mut self) {
+        self.files.insert(
+            SKIN_LIMB_SHADER.to_string(),
+            include_bytes!("skin_limb.vsh").to_vec(),
+        );
+        for part in SkinPart::ALL {
+            self.add_json(
+                format!("assets/{NAMESPACE}/items/player/{}.json", part.name()),
+                &json!({
+                    "model": {
+                        "type": "minecraft:special",
+                        "base": "minecraft:item/template_skull",
+                        "model": {
+                            "type": "minecraft:head",
+                            "kind": "player",
+                        }
+                    }
+                }),
+            );
+        }
+    }
+
+    pub fn write_zip(&self, path: &Path) -> zip::result::ZipResult<()> {
+        let mut zip = ZipWriter::new(File::create(path)?);
+        let options = SimpleFileOptions::default();
+        for (name, content) in &self.files {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(content)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+/// What the pack writes for `bone`, `None` when the bone has no element to display.
+pub fn packed_bone(model: &ModelBlueprint, bone: &BlueprintGroup) -> Option<PackedBone> {
+    let index = model.bones().iter().position(|b| b.name == bone.name)?;
+    packed(bone, &bone_paths(model)[index])
+}
+
+fn packed(bone: &BlueprintGroup, path: &str) -> Option<PackedBone> {
+    bone.elements().next()?;
+    Some(PackedBone {
+        bone: bone.name.clone(),
+        item_model: format!("{NAMESPACE}:{path}"),
+        scale: 1.0 / bone_scale(bone),
+    })
+}
+
+/// Folder of the files of `model`, named after the model.
+fn model_folder(model: &ModelBlueprint) -> String {
+    model
+        .name
+        .split('/')
+        .map(sanitize)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Item model path of every bone, in `ModelBlueprint::bones` order.
+/// Bones whose sanitized names clash, like `h_head` and `b_head`, get a `_2`, `_3`... suffix.
+fn bone_paths(model: &ModelBlueprint) -> Vec<String> {
+    let folder = model_folder(model);
+    let mut used = HashSet::new();
+    model
+        .bones()
+        .into_iter()
+        .map(|bone| {
+            let name = sanitize(bone.name.name());
+            let mut path = format!("{folder}/{name}");
+            let mut suffix = 1;
+            while !used.insert(path.clone()) {
+                suffix += 1;
+                path = format!("{folder}/{name}_{suffix}");
+            }
+            path
+        })
+        .collect()
+}
+
+/// Vertex shader of the player heads, picks the limb given by the brightness of the display.
+const SKIN_LIMB_SHADER: &str = "assets/minecraft/shaders/core/rendertype_entity_translucent.vsh";
+
+/// Item model drawing `part` with the skin of the `minecraft:profile` component of the item.
+pub fn skin_item_model(part: SkinPart) -> String {
+    format!("{NAMESPACE}:player/{}", part.name())
+}
+
+/// Shrink factor keeping every element of `bone` inside Minecraft's model bounds.
+fn bone_scale(bone: &BlueprintGroup) -> f32 {
+    let max = bone
+        .shown_elements()
+        .flat_map(|e| {
+            let inflate = Float3::flat(e.inflate);
+            [e.from.sub(&inflate), e.to.add(&inflate)]
+        })
+        .map(|v| v.sub(&bone.origin))
+        .flat_map(|v| [v.x.abs(), v.y.abs(), v.z.abs()])
+        .fold(0.0, f32::max);
+    if max > MAX_ELEMENT_OFFSET {
+        MAX_ELEMENT_OFFSET / max
+    } else {
+        1.0
+    }
+}
+
+/// Resource locations only accept `[a-z0-9_.-]`.
+pub fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            c @ ('a'..='z' | '0'..='9' | '_' | '.' | '-') => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::bone::BoneName;
+    use crate::data::blueprint::BlueprintChildren;
+    use crate::data::blueprint::BlueprintGroup;
+    use crate::data::blueprint::BlueprintTexture;
+    use crate::data::blueprint::ModelBlueprint;
+    use crate::data::blueprint::TextureAnimation;
+    use crate::data::fixture::load as blueprint;
+    use crate::data::fixture::model_data;
+    use crate::data::raw::model::ModelChildren;
+
+    use super::AtlasMode;
+    use super::PackError;
+    use super::ResourcePack;
+    use super::SKIN_LIMB_SHADER;
+    use super::packed_bone;
+
+    #[test]
+    fn test_add_model() {
+        let blueprint = blueprint();
+        let mut pack = ResourcePack::new("test");
+        pack.add_model(&blueprint).unwrap();
+
+        assert!(pack.file("pack.mcmeta").is_some());
+        assert_eq!(pack.bones("test2").len(), 4);
+        let bone: serde_json::Value = serde_json::from_slice(
+            pack.file("assets/modelblock/models/item/test2/bone.json")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(bone["elements"].as_array().unwrap().len(), 1);
+        assert!(
+            pack.files()
+                .any(|(name, _)| name.starts_with("assets/modelblock/textures/item/test2/"))
+        );
+    }
+
+    #[test]
+    fn test_skin_parts() {
+        let mut pack = ResourcePack::new("test");
+        pack.add_skin_parts();
+
+        let arm: serde_json::Value = serde_json::from_slice(
+            pack.file("assets/modelblock/items/player/right_arm.json")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(arm["model"]["model"]["kind"], "player");
+        let shader = String::from_utf8(pack.file(SKIN_LIMB_SHADER).unwrap().to_vec()).unwrap();
+        // Origine du bras droit dans le skin
+        assert!(shader.contains("vec2(40.0, 16.0)"));
+    }
+
+    #[test]
+    fn test_global_atlas() {
+        let (first, mut second) = (blueprint(), blueprint());
+        second.name = "copy".to_string();
+        let mut pack = ResourcePack::with_atlas("test", AtlasMode::Global);
+        pack.add_models(&[&first, &second]).unwrap();
+
+        let textures = pack
+            .files()
+            .filter(|(name, _)| name.ends_with(".png"))
+            .count();
+        assert_eq!(textures, 1);
+        let bone: serde_json::Value = serde_json::from_slice(
+            pack.file("assets/modelblock/models/item/copy/bone.json")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(bone["textures"]["atlas"], "modelblock:item/atlas_0");
+        for face in bone["elements"][0]["faces"].as_object().unwrap().values() {
+            assert_eq!(face["texture"], "#atlas");
+            for uv in face["uv"].as_array().unwrap() {
+                let uv = uv.as_f64().unwrap();
+                assert!((0.0..=16.0).contains(&uv));
+            }
+        }
+    }
+
+    #[test]
+    fn test_animated_texture_mcmeta() {
+        let mut blueprint = blueprint();
+        blueprint.textures[0].animation = Some(TextureAnimation {
+            frame_count: 2,
+            frame_time: 4,
+            interpolate: false,
+            frames: vec![0, 1],
+        });
+        let mut pack = ResourcePack::with_atlas("test", AtlasMode::PerModel);
+        pack.add_model(&blueprint).unwrap();
+
+        let name = "assets/modelblock/textures/item/test2/red.png";
+        let mcmeta: serde_json::Value =
+            serde_json::from_slice(pack.file(&format!("{name}.mcmeta")).unwrap()).unwrap();
+        assert_eq!(mcmeta["animation"]["frametime"], 4);
+        let (width, height) = blueprint.textures[0].image.dimensions();
+        assert_eq!(mcmeta["animation"]["width"], width);
+        assert_eq!(mcmeta["animation"]["height"], height / 2);
+        assert!(pack.file(name).is_some());
+        // Toutes les textures sont animées, aucun atlas
+        assert!(
+            pack.file("assets/modelblock/textures/item/test2/atlas.png")
+                .is_none()
+        );
+        let bone: serde_json::Value = serde_json::from_slice(
+            pack.file("assets/modelblock/models/item/test2/bone.json")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(bone["textures"]["0"], "modelblock:item/test2/red");
+    }
+
+    #[test]
+    fn test_name_clashes() {
+        let mut blueprint = blueprint();
+        // bone3 devient b_Bone2, de même nom que bone2 une fois nettoyé
+        for child in &mut blueprint.group {
+            if let BlueprintChildren::Group(group) = child {
+                for child in &mut group.children {
+                    if let BlueprintChildren::Group(group) = child
+                        && group.name.name() == "bone3"
+                    {
+                        group.name = BoneName::new(
+                            HashSet::new(),
+                            "Bone2".to_string(),
+                            "b_Bone2".to_string(),
+                        );
+                    }
+                }
+            }
+        }
+        let red = &blueprint.textures[0];
+        let copy = BlueprintTexture {
+            name: "Red.png".to_string(),
+            image: red.image.clone(),
+            uv_width: red.uv_width,
+            uv_height: red.uv_height,
+            animation: None,
+        };
+        blueprint.textures.push(copy);
+        let mut pack = ResourcePack::new("test");
+        pack.add_model(&blueprint).unwrap();
+        assert!(
+            pack.file("assets/modelblock/textures/item/test2/red_2.png")
+                .is_some()
+        );
+        assert!(
+            pack.file("assets/modelblock/models/item/test2/bone2.json")
+                .is_some()
+        );
+        assert!(
+            pack.file("assets/modelblock/models/item/test2/bone2_2.json")
+                .is_some()
+        );
+        let renamed = blueprint
+            .bones()
+            .into_iter()
+            .find(|b| b.name.name() == "Bone2")
+            .unwrap();
+        assert_eq!(
+            packed_bone(&blueprint, renamed).unwrap().item_model,
+            "modelblock:test2/bone2_2"
+        );
+
+        let mut copy = self::blueprint();
+        copy.name = "Test2".to_string();
+        assert!(matches!(
+            pack.add_model(&copy),
+            Err(PackError::NameClash { path, .. }) if path == "test2"
+        ));
+    }
+
+    #[test]
+    fn test_root_elements() {
+        let mut model = model_data();
+        let uuid = model.elements[0].uuid.clone();
+        model.outliner.push(ModelChildren::Element(uuid));
+        let blueprint = ModelBlueprint::try_from(model).unwrap();
+
+        let mut pack = ResourcePack::new("test");
+        pack.add_model(&blueprint).unwrap();
+        assert_eq!(pack.bones("test2").len(), 5);
+        assert!(
+            pack.file("assets/modelblock/models/item/test2/root.json")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_hidden_elements() {
+        fn root_bone<'a>(blueprint: &'a mut ModelBlueprint, name: &str) -> &'a mut BlueprintGroup {
+            blueprint
+                .group
+                .iter_mut()
+                .find_map(|c| match c {
+                    BlueprintChildren::Group(g) if g.name.raw_name() == name => Some(g),
+                    _ => None,
+                })
+                .unwrap()
+        }
+        fn element_count(blueprint: &ModelBlueprint) -> usize {
+            let mut pack = ResourcePack::new("test");
+            pack.add_model(blueprint).unwrap();
+            let json: serde_json::Value = serde_json::from_slice(
+                pack.file("assets/modelblock/models/item/test2/bone.json")
+                    .unwrap(),
+            )
+            .unwrap();
+            json["elements"].as_array().unwrap().len()
+        }
+
+        // Copie cachée d'un élément visible
+        let mut blueprint = blueprint();
+        let bone = root_bone(&mut blueprint, "bone");
+        let mut element = bone.elements().next().unwrap().clone();
+        element.visibility = false;
+        bone.children.push(BlueprintChildren::Element(element));
+        assert_eq!(element_count(&blueprint), 1);
+
+        // Un os sans élément visible garde ses éléments, il commence caché
+        let bone = root_bone(&mut blueprint, "bone");
+        for child in &mut bone.children {
+            if let BlueprintChildren::Element(element) = child {
+                element.visibility = false;
+            }
+        }
+        assert!(!bone.visible());
+        assert_eq!(element_count(&blueprint), 2);
+    }
+
+    #[test]
+    fn test_max_atlas_size() {
+        let blueprint = blueprint();
+        let mut pack = ResourcePack::with_atlas("test", AtlasMode::PerModel);
+        pack.set_max_atlas_size(8);
+        pack.add_model(&blueprint).unwrap();
+
+        assert!(
+            pack.file("assets/modelblock/textures/item/test2/atlas.png")
+                .is_none()
+        );
+        let bone: serde_json::Value = serde_json::from_slice(
+            pack.file("assets/modelblock/models/item/test2/bone.json")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(bone["textures"]["0"], "modelblock:item/test2/red");
+    }
+}
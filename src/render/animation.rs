@@ -0,0 +1,807 @@
+use std::collections::HashMap;
+use std::ops::Bound;
+use std::sync::Arc;
+
+use pumpkin_util::math::vector3::Vector3;
+
+use crate::bone::BoneName;
+use crate::data::blueprint::ModelBlueprint;
+use crate::data::blueprint::animation::AnimationMovement;
+use crate::data::blueprint::animation::AnimationPoint;
+use crate::data::blueprint::animation::AnimationType;
+use crate::data::blueprint::animation::BlueprintAnimation;
+use crate::data::blueprint::script::TimeScript;
+use crate::utils::VectorPoint;
+use crate::utils::interpolate::VectorInterpolation;
+use crate::utils::interpolate::default_interpolation;
+use crate::utils::math;
+use crate::utils::molang::MolangContext;
+
+use super::BonePose;
+use super::Listener;
+use super::PendingEvent;
+
+pub const TICK_SECONDS: f32 = math::MINECRAFT_TICK_MILIS as f32 / 1000.0;
+
+/// Speed and start offset of a played animation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnimationModifier {
+    /// Multiplies the playback speed, negative values play backwards
+    pub speed: f32,
+    /// Seconds skipped at the start, counted from the end when played backwards
+    pub start: f32,
+}
+
+impl AnimationModifier {
+    pub const DEFAULT: AnimationModifier = AnimationModifier::new(1.0, 0.0);
+
+    pub const fn new(speed: f32, start: f32) -> Self {
+        Self { speed, start }
+    }
+
+    pub const fn speed(speed: f32) -> Self {
+        Self::new(speed, 0.0)
+    }
+
+    pub const fn reverse() -> Self {
+        Self::new(-1.0, 0.0)
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        self.speed < 0.0
+    }
+}
+
+impl Default for AnimationModifier {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+struct RunningAnimation {
+    name: String,
+    loop_type: AnimationType,
+    speed: f32,
+    time: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransitionEvent {
+    Start {
+        from: String,
+        to: String,
+        duration: f32,
+    },
+    Finish {
+        to: String,
+    },
+    Cancel {
+        to: String,
+    },
+}
+
+pub type TransitionListener = Listener<TransitionEvent>;
+
+/// Blend from the poses sampled when the transition started to the live poses.
+struct Transition {
+    to: String,
+    from: HashMap<BoneName, AnimationPoint>,
+    duration: f32,
+    elapsed: f32,
+}
+
+/// Animations playing on one model instance, later ones are layered on top of earlier ones.
+pub struct AnimationController {
+    model: Arc<ModelBlueprint>,
+    running: Vec<RunningAnimation>,
+    time_scale: f32,
+    transition: Option<Transition>,
+    transition_duration: f32,
+    transition_interpolation: Arc<dyn VectorInterpolation + Sync + Send>,
+    transition_listeners: Vec<TransitionListener>,
+    /// Transition changes since the last `take_transition_events`
+    transition_events: Vec<TransitionEvent>,
+    /// Scripts reached since the last `take_scripts`
+    scripts: Vec<Arc<TimeScript>>,
+    /// Seconds played since creation, read by `query.life_time`
+    life_time: f32,
+    variables: HashMap<String, f32>,
+}
+
+impl AnimationController {
+    pub fn new(model: Arc<ModelBlueprint>) -> Self {
+        Self {
+            model,
+            running: Vec::new(),
+            time_scale: 1.0,
+            transition: None,
+            transition_duration: 0.0,
+            transition_interpolation: default_interpolation(),
+            transition_listeners: Vec::new(),
+            transition_events: Vec::new(),
+            scripts: Vec::new(),
+            life_time: 0.0,
+            variables: HashMap::new(),
+        }
+    }
+
+    /// Seconds used by `transition` to blend two animations, 0 snaps.
+    pub fn set_transition_duration(&mut self, seconds: f32) {
+        self.transition_duration = seconds.max(0.0);
+    }
+
+    /// Curve of the blend, linear by default.
+    pub fn set_transition_interpolation(
+        &mut self,
+        interpolation: Arc<dyn VectorInterpolation + Sync + Send>,
+    ) {
+        self.transition_interpolation = interpolation;
+    }
+
+    pub fn add_transition_listener(&mut self, listener: TransitionListener) {
+        self.transition_listeners.push(listener);
+    }
+
+    fn transition_event(&mut self, event: TransitionEvent) {
+        self.transition_events.push(event);
+    }
+
+    /// Transition starts, ends and cancels since the last call with the listeners, dispatched by the caller.
+    pub fn take_transition_events(&mut self) -> Vec<PendingEvent<TransitionEvent>> {
+        self.transition_events
+            .drain(..)
+            .map(|event| PendingEvent::new(event, &self.transition_listeners))
+            .collect()
+    }
+
+    /// Stops `from` and plays `to`, blending the bones from their current pose over the transition duration.
+    pub fn transition(&mut self, from: &str, to: &str) -> bool {
+        if !self.model.animations.contains_key(to) {
+            return false;
+        }
+        let snapshot = self
+            .poses()
+            .into_iter()
+            .map(|(bone, pose)| (bone, self.point(&pose, 0.0)))
+            .collect();
+        self.cancel_transition();
+        self.stop(from);
+        self.play(to);
+        if self.transition_duration <= 0.0 {
+            return true;
+        }
+        self.transition = Some(Transition {
+            to: to.to_string(),
+            from: snapshot,
+            duration: self.transition_duration,
+            elapsed: 0.0,
+        });
+        self.transition_event(TransitionEvent::Start {
+            from: from.to_string(),
+            to: to.to_string(),
+            duration: self.transition_duration,
+        });
+        true
+    }
+
+    /// Ends the running transition, the incoming animation is shown as is.
+    pub fn cancel_transition(&mut self) -> bool {
+        let Some(transition) = self.transition.take() else {
+            return false;
+        };
+        self.transition_event(TransitionEvent::Cancel { to: transition.to });
+        true
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Progress of the running transition, from 0 to 1.
+    pub fn transition_progress(&self) -> Option<f32> {
+        self.transition
+            .as_ref()
+            .map(|t| (t.elapsed / t.duration).min(1.0))
+    }
+
+    fn point(&self, pose: &BonePose, time: f32) -> AnimationPoint {
+        let point = |vector| VectorPoint::new(vector, time, self.transition_interpolation.clone());
+        AnimationPoint {
+            position: point(pose.position),
+            rotation: point(pose.rotation),
+            scale: point(pose.scale),
+        }
+    }
+
+    /// Plays `name` with the loop mode of the model, returns `false` if the model has no such animation.
+    pub fn play(&mut self, name: &str) -> bool {
+        let Some(animation) = self.model.animations.get(name) else {
+            return false;
+        };
+        self.play_with(name, animation.loop_type())
+    }
+
+    /// Plays `name` from the start on top of the other layers, restarting it if already playing.
+    pub fn play_with(&mut self, name: &str, loop_type: AnimationType) -> bool {
+        self.start(name, loop_type, AnimationModifier::DEFAULT)
+    }
+
+    pub fn play_with_modifier(&mut self, name: &str, modifier: AnimationModifier) -> bool {
+        let Some(animation) = self.model.animations.get(name) else {
+            return false;
+        };
+        self.start(name, animation.loop_type(), modifier)
+    }
+
+    /// Starts `name` on top of the layers, the playing instance of the same animation is replaced.
+    pub fn start(
+        &mut self,
+        name: &str,
+        loop_type: AnimationType,
+        modifier: AnimationModifier,
+    ) -> bool {
+        let Some(animation) = self.model.animations.get(name) else {
+            return false;
+        };
+        let length = animation.length();
+        let start = modifier.start.clamp(0.0, length.max(0.0));
+        let time = if modifier.is_reversed() {
+            length - start
+        } else {
+            start
+        };
+        self.running.retain(|r| r.name != name);
+        self.scripts.extend(
+            animation
+                .script()
+                .between((Bound::Included(time), Bound::Included(time)))
+                .cloned(),
+        );
+        self.running.push(RunningAnimation {
+            name: name.to_string(),
+            loop_type,
+            speed: modifier.speed,
+            time,
+        });
+        true
+    }
+
+    pub fn speed(&self, name: &str) -> Option<f32> {
+        self.running
+            .iter()
+            .find(|r| r.name == name)
+            .map(|r| r.speed)
+    }
+
+    /// Changes the speed of a playing animation without restarting it.
+    pub fn set_speed(&mut self, name: &str, speed: f32) -> bool {
+        match self.running.iter_mut().find(|r| r.name == name) {
+            Some(running) => {
+                running.speed = speed;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Scales the time of every animation and transition of this instance.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale.max(0.0);
+    }
+
+    pub fn life_time(&self) -> f32 {
+        self.life_time
+    }
+
+    pub fn variable(&self, name: &str) -> Option<f32> {
+        self.variables.get(name).copied()
+    }
+
+    /// Sets `variable.<name>` for the Molang keyframes of this instance.
+    pub fn set_variable(&mut self, name: impl Into<String>, value: f32) {
+        self.variables.insert(name.into(), value);
+    }
+
+    pub fn stop(&mut self, name: &str) -> bool {
+        let count = self.running.len();
+        self.running.retain(|r| r.name != name);
+        count != self.running.len()
+    }
+
+    pub fn stop_all(&mut self) {
+        self.running.clear();
+    }
+
+    pub fn is_playing(&self, name: &str) -> bool {
+        self.running.iter().any(|r| r.name == name)
+    }
+
+    /// Playing animations from the lowest layer to the highest.
+    pub fn playing(&self) -> impl Iterator<Item = &str> {
+        self.running.iter().map(|r| r.name.as_str())
+    }
+
+    pub fn time(&self, name: &str) -> Option<f32> {
+        self.running.iter().find(|r| r.name == name).map(|r| r.time)
+    }
+
+    pub fn tick(&mut self) {
+        self.advance(TICK_SECONDS);
+    }
+
+    /// Moves every animation by its speed, `once` animations are removed past their last frame,
+    /// or their first one when played backwards.
+    pub fn advance(&mut self, seconds: f32) {
+        let seconds = seconds * self.time_scale;
+        self.life_time += seconds;
+        if let Some(transition) = &mut self.transition {
+            transition.elapsed += seconds;
+            if transition.elapsed >= transition.duration {
+                let to = transition.to.clone();
+                self.transition = None;
+                self.transition_event(TransitionEvent::Finish { to });
+            }
+        }
+        let animations = &self.model.animations;
+        let scripts = &mut self.scripts;
+        self.running.retain_mut(|running| {
+            let Some(animation) = animations.get(&running.name) else {
+                return false;
+            };
+            let delta = seconds * running.speed;
+            let time = running
+                .loop_type
+                .advance(running.time, delta, animation.length());
+            Self::reached(animation, running.time, time, delta, scripts);
+            match time {
+                Some(time) => {
+                    running.time = time;
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
+    /// Collects the scripts passed while moving from `from` to `to`, wrapping around for loops.
+    fn reached(
+        animation: &BlueprintAnimation,
+        from: f32,
+        to: Option<f32>,
+        delta: f32,
+        scripts: &mut Vec<Arc<TimeScript>>,
+    ) {
+        use Bound::Excluded;
+        use Bound::Included;
+        let script = animation.script();
+        let length = animation.length();
+        let mut collect = |range: (Bound<f32>, Bound<f32>)| {
+            let mut passed = script.between(range).cloned().collect::<Vec<_>>();
+            if delta < 0.0 {
+                passed.reverse();
+            }
+            scripts.extend(passed);
+        };
+        if delta > 0.0 {
+            match to {
+                Some(to) if to >= from => collect((Excluded(from), Included(to))),
+                Some(to) => {
+                    collect((Excluded(from), Included(length)));
+                    collect((Included(0.0), Included(to)));
+                }
+                None => collect((Excluded(from), Included(length))),
+            }
+        } else if delta < 0.0 {
+            match to {
+                Some(to) if to <= from => collect((Included(to), Excluded(from))),
+                Some(to) => {
+                    collect((Included(0.0), Excluded(from)));
+                    collect((Included(to), Included(length)));
+                }
+                None => collect((Included(0.0), Excluded(from))),
+            }
+        }
+    }
+
+    /// Scripts reached by the playing animations since the last call, in the order they were passed.
+    pub fn take_scripts(&mut self) -> Vec<Arc<TimeScript>> {
+        std::mem::take(&mut self.scripts)
+    }
+
+    /// Pose of every animated bone, blended with the running transition.
+    pub fn poses(&self) -> HashMap<BoneName, BonePose> {
+        let poses = self.layered_poses();
+        let Some(transition) = &self.transition else {
+            return poses;
+        };
+        let interpolation = &self.transition_interpolation;
+        let blend = |from: &VectorPoint, to: Vector3<f32>| {
+            let points = [
+                from.clone(),
+                VectorPoint::new(to, transition.duration, interpolation.clone()),
+            ];
+            interpolation
+                .interpolate(&points, 1, transition.elapsed)
+                .vector
+        };
+        let empty = self.point(&BonePose::EMPTY, 0.0);
+        let mut bones = poses.keys().cloned().collect::<Vec<_>>();
+        bones.extend(
+            transition
+                .from
+                .keys()
+                .filter(|b| !poses.contains_key(*b))
+                .cloned(),
+        );
+        bones
+            .into_iter()
+            .map(|bone| {
+                let from = transition.from.get(&bone).unwrap_or(&empty);
+                let to = poses.get(&bone).copied().unwrap_or_default();
+                let pose = BonePose {
+                    position: blend(&from.position, to.position),
+                    rotation: blend(&from.rotation, to.rotation),
+                    scale: blend(&from.scale, to.scale),
+                };
+                (bone, pose)
+            })
+            .collect()
+    }
+
+    /// Layered pose of every animated bone.
+    fn layered_poses(&self) -> HashMap<BoneName, BonePose> {
+        let mut poses: HashMap<BoneName, BonePose> = HashMap::new();
+        let mut context = MolangContext {
+            anim_time: 0.0,
+            life_time: self.life_time,
+            variables: self.variables.clone(),
+        };
+        for running in &self.running {
+            let Some(animation) = self.model.animations.get(&running.name) else {
+                continue;
+            };
+            context.anim_time = running.time;
+            for (bone, animator) in animation.animators() {
+                let pose = BonePose::from(&animator.sample_with(running.time, &context));
+                let entry = poses.entry(bone.clone()).or_default();
+                // Une animation prioritaire remplace les couches inférieures
+                *entry = if animation.overriding() {
+                    pose
+                } else {
+                    entry.add(&pose)
+                };
+            }
+        }
+        poses
+    }
+}
+
+impl From<&AnimationMovement> for BonePose {
+    fn from(value: &AnimationMovement) -> Self {
+        let zero = Vector3::new(0.0, 0.0, 0.0);
+        Self {
+            position: value.position().unwrap_or(zero),
+            rotation: value.rotation().unwrap_or(zero),
+            scale: value.scale().unwrap_or(zero),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use pumpkin_util::math::vector3::Vector3;
+    use serde_json::Value;
+    use serde_json::json;
+
+    use crate::data::blueprint::ModelBlueprint;
+    use crate::data::blueprint::animation::AnimationType;
+    use crate::data::blueprint::script::RenderSource;
+    use crate::data::blueprint::script::ScriptEffect;
+    use crate::data::fixture;
+    use crate::data::fixture::bone;
+    use crate::data::raw::model::ModelData;
+
+    use super::AnimationController;
+    use super::AnimationModifier;
+    use super::TransitionEvent;
+
+    const WALK: &str = "animation.model.new";
+
+    /// test2.json with two copies of its animation restricted to bone2, `attack` being overriding.
+    fn blueprint() -> Arc<ModelBlueprint> {
+        let mut json = fixture::json();
+        let animations = json["animations"].as_array_mut().unwrap();
+        for (name, overriding) in [("wave", false), ("attack", true)] {
+            let mut copy = animations[0].clone();
+            copy["name"] = name.into();
+            copy["loop"] = "once".into();
+            copy["override"] = overriding.into();
+            copy["animators"]
+                .as_object_mut()
+                .unwrap()
+                .retain(|_, animator| animator["name"] == "bone2");
+            if name == "wave" {
+                copy["animators"]["effects"] = json!({
+                    "name": "Effects",
+                    "type": "effect",
+                    "keyframes": [
+                        effect("particle", 0.25, json!({"effect": "minecraft:flame", "locator": "bone2"})),
+                        effect("sound", 0.5, json!({"effect": "entity.pig.ambient"})),
+                        effect("timeline", 0.75, json!({"script": "command:/say hi"})),
+                    ],
+                });
+            }
+            animations.push(copy);
+        }
+        let mut molang = animations[0].clone();
+        molang["name"] = "molang".into();
+        let animators = molang["animators"].as_object_mut().unwrap();
+        animators.retain(|_, animator| animator["name"] == "bone2");
+        for animator in animators.values_mut() {
+            animator["keyframes"] = json!([{
+                "channel": "rotation",
+                "data_points": [{"x": "v.tilt + query.anim_time * 10", "y": "math.pi * 0", "z": 0}],
+                "uuid": "molang",
+                "time": 0.0,
+            }]);
+        }
+        animations.push(molang);
+        let mut curve = animations[0].clone();
+        curve["name"] = "curve".into();
+        let animators = curve["animators"].as_object_mut().unwrap();
+        animators.retain(|_, animator| animator["name"] == "bone2");
+        for animator in animators.values_mut() {
+            animator["keyframes"] = json!([0.0, 0.5, 1.0].map(|time| json!({
+                "channel": "rotation",
+                "data_points": [{"x": if time == 0.5 { 90 } else { 0 }, "y": 0, "z": 0}],
+                "uuid": format!("curve-{time}"),
+                "time": time,
+                "interpolation": "catmullrom",
+            })));
+        }
+        animations.push(curve);
+        let model: ModelData = serde_json::from_value(json).unwrap();
+        Arc::new(ModelBlueprint::try_from(model).unwrap())
+    }
+
+    fn effect(channel: &str, time: f32, data_point: Value) -> Value {
+        json!({
+            "channel": channel,
+            "data_points": [data_point],
+            "uuid": format!("{channel}-{time}"),
+            "time": time,
+        })
+    }
+
+    fn rotation_x(controller: &AnimationController, name: &str) -> f32 {
+        controller.poses()[&bone(name)].rotation.x
+    }
+
+    #[test]
+    fn test_play_stop() {
+        let mut controller = AnimationController::new(blueprint());
+        assert!(!controller.play("missing"));
+        assert!(controller.play(WALK));
+        assert!(controller.is_playing(WALK));
+
+        // 0.25s : bone2 atteint sa première clé de rotation
+        for _ in 0..5 {
+            controller.tick();
+        }
+        assert!((rotation_x(&controller, "bone2") - 75.0).abs() < 1e-3);
+
+        // La boucle repart au début
+        for _ in 0..20 {
+            controller.tick();
+        }
+        assert!((controller.time(WALK).unwrap() - 0.25).abs() < 1e-3);
+
+        assert!(controller.stop(WALK));
+        assert!(!controller.stop(WALK));
+        assert!(controller.poses().is_empty());
+    }
+
+    #[test]
+    fn test_additive_layers() {
+        let mut controller = AnimationController::new(blueprint());
+        controller.play(WALK);
+        controller.play("wave");
+        assert_eq!(controller.playing().collect::<Vec<_>>(), vec![WALK, "wave"]);
+        for _ in 0..5 {
+            controller.tick();
+        }
+        assert!((rotation_x(&controller, "bone2") - 150.0).abs() < 1e-3);
+        assert!((rotation_x(&controller, "bone3") + 17.5).abs() < 1e-3);
+
+        // Rejouer une animation la remet au début sans dupliquer la couche
+        controller.play_with(WALK, AnimationType::Loop);
+        assert_eq!(controller.playing().collect::<Vec<_>>(), vec!["wave", WALK]);
+        assert_eq!(controller.time(WALK), Some(0.0));
+    }
+
+    #[test]
+    fn test_overriding_layer() {
+        let mut controller = AnimationController::new(blueprint());
+        controller.play(WALK);
+        controller.play("attack");
+        controller.advance(0.25);
+        // bone2 ne prend que la couche prioritaire, bone3 garde la marche
+        assert!((rotation_x(&controller, "bone2") - 75.0).abs() < 1e-3);
+        assert!((rotation_x(&controller, "bone3") + 17.5).abs() < 1e-3);
+
+        // Une animation `once` s'arrête après sa dernière frame
+        controller.advance(1.0);
+        assert!(!controller.is_playing("attack"));
+        assert!(controller.is_playing(WALK));
+    }
+
+    #[test]
+    fn test_transition() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut controller = AnimationController::new(blueprint());
+        let received = events.clone();
+        controller.add_transition_listener(Arc::new(move |event| {
+            received.lock().unwrap().push(event.clone())
+        }));
+        let dispatch = |controller: &mut AnimationController| {
+            for event in controller.take_transition_events() {
+                event.dispatch();
+            }
+        };
+        controller.set_transition_duration(0.2);
+        controller.play(WALK);
+        controller.advance(0.25);
+
+        // La marche est à 75° sur bone2, `wave` commence à 0°
+        assert!(controller.transition(WALK, "wave"));
+        assert!(!controller.is_playing(WALK));
+        assert!((rotation_x(&controller, "bone2") - 75.0).abs() < 1e-3);
+        // Les listeners attendent que l'appelant relâche le verrou
+        assert!(events.lock().unwrap().is_empty());
+        dispatch(&mut controller);
+        assert_eq!(events.lock().unwrap().len(), 1);
+        controller.advance(0.1);
+        assert_eq!(controller.transition_progress(), Some(0.5));
+        // Moitié entre 75° et wave à 0.1s (30°)
+        assert!((rotation_x(&controller, "bone2") - 52.5).abs() < 1e-3);
+        // bone3 n'est plus animé et revient vers 0
+        assert!((rotation_x(&controller, "bone3") + 8.75).abs() < 1e-3);
+
+        controller.advance(0.1);
+        assert!(!controller.is_transitioning());
+        assert!((rotation_x(&controller, "bone2") - 60.0).abs() < 1e-3);
+
+        controller.transition("wave", WALK);
+        assert!(controller.cancel_transition());
+        assert!(!controller.cancel_transition());
+        dispatch(&mut controller);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                TransitionEvent::Start {
+                    from: WALK.to_string(),
+                    to: "wave".to_string(),
+                    duration: 0.2,
+                },
+                TransitionEvent::Finish {
+                    to: "wave".to_string()
+                },
+                TransitionEvent::Start {
+                    from: "wave".to_string(),
+                    to: WALK.to_string(),
+                    duration: 0.2,
+                },
+                TransitionEvent::Cancel {
+                    to: WALK.to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reverse_and_speed() {
+        let mut controller = AnimationController::new(blueprint());
+        controller.play_with_modifier("wave", AnimationModifier::reverse());
+        assert_eq!(controller.time("wave"), Some(1.0));
+        controller.advance(0.75);
+        assert!((rotation_x(&controller, "bone2") - 75.0).abs() < 1e-3);
+        controller.advance(0.5);
+        assert!(!controller.is_playing("wave"));
+
+        // Une boucle jouée à l'envers repart de la fin
+        controller.start(WALK, AnimationType::Loop, AnimationModifier::new(-2.0, 0.5));
+        assert_eq!(controller.time(WALK), Some(0.5));
+        controller.advance(0.5);
+        assert!((controller.time(WALK).unwrap() - 0.5).abs() < 1e-3);
+
+        assert!(controller.set_speed(WALK, 0.5));
+        controller.set_time_scale(2.0);
+        controller.advance(0.25);
+        assert!((controller.time(WALK).unwrap() - 0.75).abs() < 1e-3);
+        assert!(!controller.set_speed("wave", 1.0));
+    }
+
+    #[test]
+    fn test_scripts() {
+        let mut controller = AnimationController::new(blueprint());
+        let run = |controller: &mut AnimationController, seconds: f32| {
+            controller.advance(seconds);
+            let bones = [(bone("bone2"), Vector3::new(0.0, 1.0, 0.0))].into();
+            let mut source = RenderSource::new(Vector3::new(0.0, 0.0, 0.0), bones);
+            let scripts = controller.take_scripts();
+            for script in &scripts {
+                script.run(&mut source);
+            }
+            let timeline = scripts
+                .iter()
+                .filter_map(|s| s.source().map(str::to_string))
+                .collect::<Vec<_>>();
+            (source.take_effects(), timeline)
+        };
+
+        controller.play("wave");
+        let (effects, timeline) = run(&mut controller, 0.3);
+        assert_eq!(
+            effects,
+            vec![(
+                ScriptEffect::Particle("minecraft:flame".to_string()),
+                Vector3::new(0.0, 1.0, 0.0)
+            )]
+        );
+        assert!(timeline.is_empty());
+
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        let (effects, timeline) = run(&mut controller, 0.5);
+        assert_eq!(
+            effects,
+            vec![
+                (
+                    ScriptEffect::Sound("entity.pig.ambient".to_string()),
+                    origin
+                ),
+                (ScriptEffect::Command("say hi".to_string()), origin),
+            ]
+        );
+        assert_eq!(timeline, vec!["command:/say hi".to_string()]);
+
+        // À l'envers, la timeline est passée en premier
+        controller.play_with_modifier("wave", AnimationModifier::reverse());
+        let (effects, _) = run(&mut controller, 0.3);
+        assert_eq!(
+            effects,
+            vec![(ScriptEffect::Command("say hi".to_string()), origin)]
+        );
+    }
+
+    #[test]
+    fn test_curve() {
+        let mut controller = AnimationController::new(blueprint());
+        controller.play("curve");
+        controller.advance(0.25);
+        // Catmull-Rom entre 0° et 90°, la ligne droite donnerait 45°
+        assert!((rotation_x(&controller, "bone2") - 50.625).abs() < 1e-3);
+        controller.advance(0.25);
+        assert!((rotation_x(&controller, "bone2") - 90.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_molang() {
+        let mut controller = AnimationController::new(blueprint());
+        controller.set_variable("tilt", 5.0);
+        controller.play("molang");
+        controller.advance(0.5);
+        assert!((rotation_x(&controller, "bone2") - 10.0).abs() < 1e-4);
+        assert_eq!(controller.poses()[&bone("bone2")].rotation.y, 0.0);
+
+        controller.set_variable("tilt", -5.0);
+        assert!(rotation_x(&controller, "bone2").abs() < 1e-4);
+        assert!((controller.life_time() - 0.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,230 @@
+use pumpkin_util::math::vector3::Vector3;
+use uuid::Uuid;
+
+use crate::bone::BoneName;
+use crate::bone::BoneTag;
+use crate::data::blueprint::BlueprintGroup;
+use crate::utils::math;
+use crate::utils::math::Quaternion;
+
+use super::DisplayTransform;
+use super::Listener;
+
+/// Box of a hitbox bone relative to its pivot, in blocks and display space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HitBoxBounds {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl HitBoxBounds {
+    /// Bounds of the elements of `group`, `None` if it has none.
+    pub fn of(group: &BlueprintGroup) -> Option<Self> {
+        let mut bounds: Option<Self> = None;
+        for element in group.elements() {
+            let inflate = Vector3::new(element.inflate, element.inflate, element.inflate);
+            let from = element.from.sub(&group.origin).to_vec3().sub(&inflate);
+            let to = element.to.sub(&group.origin).to_vec3().add(&inflate);
+            let (a, b) = (
+                math::transform_to_display(from.multiply(1.0 / 16.0, 1.0 / 16.0, 1.0 / 16.0)),
+                math::transform_to_display(to.multiply(1.0 / 16.0, 1.0 / 16.0, 1.0 / 16.0)),
+            );
+            let min = Vector3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+            let max = Vector3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+            bounds = Some(match bounds {
+                Some(o) => Self {
+                    min: Vector3::new(o.min.x.min(min.x), o.min.y.min(min.y), o.min.z.min(min.z)),
+                    max: Vector3::new(o.max.x.max(max.x), o.max.y.max(max.y), o.max.z.max(max.z)),
+                },
+                None => Self { min, max },
+            });
+        }
+        bounds
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        self.min.add(&self.max).multiply(0.5, 0.5, 0.5)
+    }
+
+    pub fn half_size(&self) -> Vector3<f32> {
+        self.max.sub(&self.min).multiply(0.5, 0.5, 0.5)
+    }
+}
+
+/// Whether `name` has a hitbox, fixed or oriented.
+pub fn is_hitbox(name: &BoneName) -> bool {
+    name.has_tag(&BoneTag::HITBOX) || is_oriented(name)
+}
+
+/// Whether the hitbox of `name` rotates with its bone.
+pub fn is_oriented(name: &BoneName) -> bool {
+    name.has_tag(&BoneTag::ORIENTED_HITBOX)
+}
+
+/// A hitbox placed in the world, `rotation` is identity for `b` hitboxes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldHitBox {
+    pub center: Vector3<f64>,
+    pub half_size: Vector3<f32>,
+    pub rotation: Quaternion,
+}
+
+impl WorldHitBox {
+    pub fn new(
+        position: Vector3<f64>,
+        bone: &DisplayTransform,
+        bounds: &HitBoxBounds,
+        oriented: bool,
+    ) -> Self {
+        let center = bone
+            .translation
+            .add(
+                &bone.left_rotation.rotate(bounds.center().multiply(
+                    bone.scale.x,
+                    bone.scale.y,
+                    bone.scale.z,
+                )),
+            );
+        Self {
+            center: position.add(&Vector3::new(
+                center.x as f64,
+                center.y as f64,
+                center.z as f64,
+            )),
+            half_size: bounds.half_size().multiply(
+                bone.scale.x.abs(),
+                bone.scale.y.abs(),
+                bone.scale.z.abs(),
+            ),
+            rotation: if oriented {
+                bone.left_rotation
+            } else {
+                Quaternion::IDENTITY
+            },
+        }
+    }
+
+    /// Smallest axis aligned box containing the hitbox, as `(min, max)`.
+    pub fn aabb(&self) -> (Vector3<f64>, Vector3<f64>) {
+        let axis = [
+            self.rotation
+                .rotate(Vector3::new(self.half_size.x, 0.0, 0.0)),
+            self.rotation
+                .rotate(Vector3::new(0.0, self.half_size.y, 0.0)),
+            self.rotation
+                .rotate(Vector3::new(0.0, 0.0, self.half_size.z)),
+        ];
+        let extent = Vector3::new(
+            axis.iter().map(|a| a.x.abs()).sum::<f32>() as f64,
+            axis.iter().map(|a| a.y.abs()).sum::<f32>() as f64,
+            axis.iter().map(|a| a.z.abs()).sum::<f32>() as f64,
+        );
+        (self.center.sub(&extent), self.center.add(&extent))
+    }
+
+    /// Width and height of an interaction entity covering `aabb`.
+    pub fn interaction_size(&self) -> (f32, f32) {
+        let (min, max) = self.aabb();
+        let size = max.sub(&min);
+        (size.x.max(size.z) as f32, size.y as f32)
+    }
+
+    /// Bottom center of `aabb`, where the interaction entity stands.
+    pub fn interaction_position(&self) -> Vector3<f64> {
+        let (min, _) = self.aabb();
+        Vector3::new(self.center.x, min.y, self.center.z)
+    }
+
+    /// Distance along `direction` at which the ray enters the box.
+    pub fn ray_cast(&self, origin: Vector3<f64>, direction: Vector3<f64>) -> Option<f64> {
+        // Le rayon est ramené dans le repère de la boîte
+        let inverse = self.rotation.conjugate();
+        let relative = origin.sub(&self.center);
+        let origin = inverse.rotate(Vector3::new(
+            relative.x as f32,
+            relative.y as f32,
+            relative.z as f32,
+        ));
+        let direction = inverse.rotate(Vector3::new(
+            direction.x as f32,
+            direction.y as f32,
+            direction.z as f32,
+        ));
+        let (mut near, mut far) = (f32::NEG_INFINITY, f32::INFINITY);
+        for (o, d, h) in [
+            (origin.x, direction.x, self.half_size.x),
+            (origin.y, direction.y, self.half_size.y),
+            (origin.z, direction.z, self.half_size.z),
+        ] {
+            if d.abs() < f32::EPSILON {
+                if o.abs() > h {
+                    return None;
+                }
+                continue;
+            }
+            let (a, b) = ((-h - o) / d, (h - o) / d);
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+        }
+        (near <= far && far >= 0.0).then_some(near.max(0.0) as f64)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitKind {
+    Attack,
+    Interact,
+}
+
+/// Sent to the listeners of a model when a player hits one of its hitboxes.
+#[derive(Clone, Debug)]
+pub struct HitEvent {
+    pub bone: BoneName,
+    pub player: Uuid,
+    pub kind: HitKind,
+}
+
+pub type HitListener = Listener<HitEvent>;
+
+#[cfg(test)]
+mod test {
+    use pumpkin_util::math::vector3::Vector3;
+
+    use crate::bone::BoneTagRegistry;
+    use crate::utils::math::Quaternion;
+
+    use super::WorldHitBox;
+    use super::is_hitbox;
+    use super::is_oriented;
+
+    #[test]
+    fn test_oriented_tag() {
+        let registry = BoneTagRegistry::default();
+        let oriented = registry.parse("OB_door");
+        assert!(is_hitbox(&oriented) && is_oriented(&oriented));
+        let fixed = registry.parse("b_door");
+        assert!(is_hitbox(&fixed) && !is_oriented(&fixed));
+        assert!(!is_hitbox(&registry.parse("h_ob")));
+    }
+
+    #[test]
+    fn test_oriented_ray_cast() {
+        let hitbox = WorldHitBox {
+            center: Vector3::new(0.0, 0.0, 0.0),
+            half_size: Vector3::new(2.0, 0.25, 0.25),
+            rotation: Quaternion::from_euler(Vector3::new(0.0, 90.0, 0.0)),
+        };
+        // La boîte tournée s'étend le long de z
+        let along_z = hitbox.ray_cast(Vector3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!((along_z.unwrap() - 3.0).abs() < 1e-4);
+        assert!(
+            hitbox
+                .ray_cast(Vector3::new(1.5, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0))
+                .is_none()
+        );
+
+        let (min, max) = hitbox.aabb();
+        assert!((max.z - 2.0).abs() < 1e-4 && (min.x + 0.25).abs() < 1e-4);
+        assert!((hitbox.interaction_size().0 - 4.0).abs() < 1e-4);
+    }
+}
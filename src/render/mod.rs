@@ -0,0 +1,249 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::Ordering;
+
+use pumpkin_util::math::vector3::Vector3;
+use uuid::Uuid;
+
+use crate::bone::skin::SkinPart;
+use crate::data::blueprint::animation::AnimationPoint;
+use crate::utils::math::Quaternion;
+use crate::utils::math::is_similar_vec;
+
+pub mod animation;
+pub mod hitbox;
+pub mod pumpkin;
+pub mod registry;
+pub mod seat;
+pub mod tracker;
+
+/// Callback registered on a model, shared so the registry lock can be released before calling it.
+pub type Listener<E> = Arc<dyn Fn(&E) + Send + Sync>;
+
+/// Event of a model with the listeners to notify, taken out of the tracker registry then dispatched.
+pub struct PendingEvent<E> {
+    pub event: E,
+    listeners: Vec<Listener<E>>,
+}
+
+impl<E> PendingEvent<E> {
+    pub fn new(event: E, listeners: &[Listener<E>]) -> Self {
+        Self {
+            event,
+            listeners: listeners.to_vec(),
+        }
+    }
+
+    /// Calls the listeners, never while holding the tracker registry lock.
+    pub fn dispatch(&self) {
+        for listener in &self.listeners {
+            listener(&self.event);
+        }
+    }
+}
+
+/// Display entities only exist client side, their ids start far above the ones the server hands out.
+static NEXT_ENTITY_ID: AtomicI32 = AtomicI32::new(1 << 30);
+
+pub fn next_entity_id() -> i32 {
+    NEXT_ENTITY_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Local animated state of a bone, relative to its rest pose, in display space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BonePose {
+    /// Offset in blocks
+    pub position: Vector3<f32>,
+    /// Euler angles in degrees
+    pub rotation: Vector3<f32>,
+    /// Added to the rest scale of 1
+    pub scale: Vector3<f32>,
+}
+
+impl BonePose {
+    pub const EMPTY: BonePose = BonePose {
+        position: Vector3::new(0.0, 0.0, 0.0),
+        rotation: Vector3::new(0.0, 0.0, 0.0),
+        scale: Vector3::new(0.0, 0.0, 0.0),
+    };
+
+    /// Sum of two poses, used to layer additive animations.
+    pub fn add(&self, other: &Self) -> Self {
+        Self {
+            position: self.position.add(&other.position),
+            rotation: self.rotation.add(&other.rotation),
+            scale: self.scale.add(&other.scale),
+        }
+    }
+}
+
+impl Default for BonePose {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl From<&AnimationPoint> for BonePose {
+    fn from(value: &AnimationPoint) -> Self {
+        Self {
+            position: value.position.vector,
+            rotation: value.rotation.vector,
+            scale: value.scale.vector,
+        }
+    }
+}
+
+/// Transformation of a display entity, relative to the model location.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayTransform {
+    pub translation: Vector3<f32>,
+    pub left_rotation: Quaternion,
+    pub scale: Vector3<f32>,
+}
+
+impl DisplayTransform {
+    pub fn is_similar(&self, other: &Self) -> bool {
+        is_similar_vec(&self.translation, &other.translation)
+            && self.left_rotation.is_similar(&other.left_rotation)
+            && is_similar_vec(&self.scale, &other.scale)
+    }
+}
+
+/// Game profile whose skin is drawn by the player part bones, as in the `minecraft:profile` component.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkinProfile {
+    pub id: Uuid,
+    pub name: String,
+    /// Base64 `textures` property given by Mojang
+    pub textures: String,
+    pub signature: Option<String>,
+}
+
+/// `minecraft:custom_model_data` of an item, read by the item model definitions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CustomModelData {
+    pub floats: Vec<f32>,
+    pub flags: Vec<bool>,
+    pub strings: Vec<String>,
+    pub colors: Vec<i32>,
+}
+
+/// Item stack put in a display.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisplayItem {
+    /// Registry key of the item
+    pub item: String,
+    /// Overrides the model of the item, pack bones are papers drawn with their own model
+    pub item_model: Option<String>,
+    /// Set for the bones drawn with a player skin
+    pub profile: Option<SkinProfile>,
+    /// `minecraft:dyed_color`, tints the faces of the pack models
+    pub dyed_color: Option<i32>,
+    pub custom_model_data: Option<CustomModelData>,
+    /// `minecraft:enchantment_glint_override`
+    pub enchantment_glint: Option<bool>,
+    /// Limb of the skin the pack shader draws instead of the head
+    pub skin_part: Option<SkinPart>,
+}
+
+impl DisplayItem {
+    pub fn new(item: impl Into<String>) -> Self {
+        Self {
+            item: item.into(),
+            item_model: None,
+            profile: None,
+            dyed_color: None,
+            custom_model_data: None,
+            enchantment_glint: None,
+            skin_part: None,
+        }
+    }
+
+    /// Paper drawn with `item_model`.
+    pub fn model(item_model: impl Into<String>) -> Self {
+        Self {
+            item_model: Some(item_model.into()),
+            ..Self::new("minecraft:paper")
+        }
+    }
+
+    /// Player head drawn with `item_model` and the `part` of the skin of `profile`.
+    pub fn skin(item_model: impl Into<String>, part: SkinPart, profile: SkinProfile) -> Self {
+        Self {
+            item_model: Some(item_model.into()),
+            profile: Some(profile),
+            skin_part: Some(part),
+            ..Self::new("minecraft:player_head")
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DisplayPacket {
+    Spawn {
+        entity_id: i32,
+        position: Vector3<f64>,
+        /// `None` for an empty display
+        item: Option<DisplayItem>,
+        transform: DisplayTransform,
+    },
+    /// New item of a spawned display
+    Item {
+        entity_id: i32,
+        item: Option<DisplayItem>,
+    },
+    Transform {
+        entity_id: i32,
+        transform: DisplayTransform,
+        interpolation_ticks: i32,
+    },
+    /// Interaction entity receiving the clicks on a hitbox bone
+    SpawnHitBox {
+        entity_id: i32,
+        position: Vector3<f64>,
+        width: f32,
+        height: f32,
+    },
+    HitBoxSize {
+        entity_id: i32,
+        width: f32,
+        height: f32,
+    },
+    /// Invisible entity players ride on a seat bone
+    SpawnSeat {
+        entity_id: i32,
+        position: Vector3<f64>,
+    },
+    Passengers {
+        vehicle: i32,
+        passengers: Vec<i32>,
+    },
+    Move {
+        entity_ids: Vec<i32>,
+        position: Vector3<f64>,
+    },
+    Remove {
+        entity_ids: Vec<i32>,
+    },
+    /// Sound keyframe reached by an animation
+    Sound {
+        name: String,
+        position: Vector3<f64>,
+    },
+    /// Particle keyframe reached by an animation
+    Particle {
+        name: String,
+        position: Vector3<f64>,
+    },
+}
+
+/// Receives the packets produced by the trackers, the Pumpkin implementation lives in `pumpkin`.
+pub trait PacketSink {
+    fn send(&mut self, packet: DisplayPacket);
+}
+
+impl PacketSink for Vec<DisplayPacket> {
+    fn send(&mut self, packet: DisplayPacket) {
+        self.push(packet);
+    }
+}
@@ -0,0 +1,881 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::RwLock;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use pumpkin::command::CommandSender;
+use pumpkin::entity::Entity;
+use pumpkin::entity::EntityBase;
+use pumpkin::entity::living::LivingEntity;
+use pumpkin::entity::player::Player;
+use pumpkin::plugin::EventHandler;
+use pumpkin::plugin::player::player_input_event::PlayerInputEvent;
+use pumpkin::plugin::player::player_interact_unknown_entity_event::PlayerInteractUnknownEntityEvent;
+use pumpkin::plugin::player::player_join::PlayerJoinEvent;
+use pumpkin::plugin::player::player_leave::PlayerLeaveEvent;
+use pumpkin::server::Server;
+use pumpkin_api_macros::with_runtime;
+use pumpkin_data::data_component_impl::CustomModelDataImpl;
+use pumpkin_data::data_component_impl::DyedColorImpl;
+use pumpkin_data::data_component_impl::EnchantmentGlintOverrideImpl;
+use pumpkin_data::data_component_impl::EquipmentSlot as PumpkinEquipmentSlot;
+use pumpkin_data::data_component_impl::ItemModelImpl;
+use pumpkin_data::entity::EntityType;
+use pumpkin_data::item::Item;
+use pumpkin_data::meta_data_type::MetaDataType;
+use pumpkin_data::particle::Particle;
+use pumpkin_data::sound::Sound;
+use pumpkin_data::sound::SoundCategory;
+use pumpkin_protocol::codec::var_int::VarInt;
+use pumpkin_protocol::java::client::play::CEntityPositionSync;
+use pumpkin_protocol::java::client::play::CParticle;
+use pumpkin_protocol::java::client::play::CRemoveEntities;
+use pumpkin_protocol::java::client::play::CSetEntityMetadata;
+use pumpkin_protocol::java::client::play::CSetPassengers;
+use pumpkin_protocol::java::client::play::CSpawnEntity;
+use pumpkin_protocol::java::client::play::Metadata;
+use pumpkin_protocol::java::server::play::ActionType;
+use pumpkin_protocol::ser::serializer::Serializer;
+use pumpkin_util::math::vector3::Vector3;
+use serde::Serialize;
+use serde::ser::SerializeTuple;
+
+use crate::bone::BoneName;
+use crate::bone::item::Equipment;
+use crate::bone::item::EquipmentSlot;
+use crate::data::blueprint::ModelBlueprint;
+
+use super::CustomModelData;
+use super::DisplayItem;
+use super::DisplayPacket;
+use super::DisplayTransform;
+use super::PendingEvent;
+use super::SkinProfile;
+use super::hitbox::HitKind;
+use super::registry::TrackerId;
+use super::registry::ViewerPackets;
+use super::registry::get_tracker_registry;
+use super::seat::PlayerInput;
+use super::seat::SeatError;
+use super::tracker::DEFAULT_UPDATE_INTERVAL_MILIS;
+use super::tracker::ModelTracker;
+
+// Index des metadata d'un item display
+const INTERPOLATION_DELAY: u8 = 8;
+const TRANSFORMATION_INTERPOLATION_DURATION: u8 = 9;
+const TELEPORT_DURATION: u8 = 10;
+const TRANSLATION: u8 = 11;
+const SCALE: u8 = 12;
+const LEFT_ROTATION: u8 = 13;
+const BRIGHTNESS_OVERRIDE: u8 = 16;
+const ITEM: u8 = 23;
+const METADATA_END: u8 = 0xFF;
+
+// Index des metadata d'une entité interaction
+const HITBOX_WIDTH: u8 = 8;
+const HITBOX_HEIGHT: u8 = 9;
+const HITBOX_RESPONSE: u8 = 10;
+
+/// Data component id of `minecraft:item_model`.
+const ITEM_MODEL_COMPONENT: i32 = 7;
+/// Data component id of `minecraft:custom_model_data`.
+const CUSTOM_MODEL_DATA_COMPONENT: i32 = 14;
+/// Data component id of `minecraft:enchantment_glint_override`.
+const ENCHANTMENT_GLINT_COMPONENT: i32 = 19;
+/// Data component id of `minecraft:dyed_color`.
+const DYED_COLOR_COMPONENT: i32 = 34;
+/// Data component id of `minecraft:profile`.
+const PROFILE_COMPONENT: i32 = 57;
+
+/// Item stack of a display, an empty stack when `None`.
+struct DisplayItemStack<'a>(Option<&'a DisplayItem>);
+
+impl Serialize for DisplayItemStack<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(19)?;
+        let Some((display, item)) = self
+            .0
+            .and_then(|d| Some((d, Item::from_registry_key(strip_namespace(&d.item))?)))
+        else {
+            tuple.serialize_element(&VarInt(0))?;
+            return tuple.end();
+        };
+        tuple.serialize_element(&VarInt(1))?;
+        tuple.serialize_element(&VarInt(item.id as i32))?;
+        // Composants ajoutés, aucun retiré
+        let added = display.item_model.is_some() as i32
+            + display.custom_model_data.is_some() as i32
+            + display.enchantment_glint.is_some() as i32
+            + display.dyed_color.is_some() as i32
+            + display.profile.is_some() as i32;
+        tuple.serialize_element(&VarInt(added))?;
+        tuple.serialize_element(&VarInt(0))?;
+        if let Some(item_model) = &display.item_model {
+            tuple.serialize_element(&VarInt(ITEM_MODEL_COMPONENT))?;
+            tuple.serialize_element(item_model)?;
+        }
+        if let Some(data) = &display.custom_model_data {
+            // Quatre listes préfixées par leur taille
+            tuple.serialize_element(&VarInt(CUSTOM_MODEL_DATA_COMPONENT))?;
+            tuple.serialize_element(&VarInt(data.floats.len() as i32))?;
+            for value in &data.floats {
+                tuple.serialize_element(value)?;
+            }
+            tuple.serialize_element(&VarInt(data.flags.len() as i32))?;
+            for value in &data.flags {
+                tuple.serialize_element(value)?;
+            }
+            tuple.serialize_element(&VarInt(data.strings.len() as i32))?;
+            for value in &data.strings {
+                tuple.serialize_element(value)?;
+            }
+            tuple.serialize_element(&VarInt(data.colors.len() as i32))?;
+            for value in &data.colors {
+                tuple.serialize_element(value)?;
+            }
+        }
+        if let Some(glint) = display.enchantment_glint {
+            tuple.serialize_element(&VarInt(ENCHANTMENT_GLINT_COMPONENT))?;
+            tuple.serialize_element(&glint)?;
+        }
+        if let Some(color) = display.dyed_color {
+            // Couleur puis affichage dans l'infobulle
+            tuple.serialize_element(&VarInt(DYED_COLOR_COMPONENT))?;
+            tuple.serialize_element(&color)?;
+            tuple.serialize_element(&false)?;
+        }
+        if let Some(profile) = &display.profile {
+            tuple.serialize_element(&VarInt(PROFILE_COMPONENT))?;
+            // Nom et uuid optionnels, puis une seule propriété `textures`
+            tuple.serialize_element(&true)?;
+            tuple.serialize_element(&profile.name)?;
+            tuple.serialize_element(&true)?;
+            let (most, least) = profile.id.as_u64_pair();
+            tuple.serialize_element(&most)?;
+            tuple.serialize_element(&least)?;
+            tuple.serialize_element(&VarInt(1))?;
+            tuple.serialize_element("textures")?;
+            tuple.serialize_element(&profile.textures)?;
+            tuple.serialize_element(&profile.signature.is_some())?;
+            if let Some(signature) = &profile.signature {
+                tuple.serialize_element(signature)?;
+            }
+        }
+        tuple.end()
+    }
+}
+
+struct Quaternion([f32; 4]);
+
+impl Serialize for Quaternion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(4)?;
+        for value in self.0 {
+            tuple.serialize_element(&value)?;
+        }
+        tuple.end()
+    }
+}
+
+/// Brightness override of a display showing `item`, `-1` keeps the light of the world.
+/// The limbs drawn by a player head pass their marker to the pack shader in the block light,
+/// above the 15 levels vanilla ever packs, and get the full sky light.
+fn brightness(item: Option<&DisplayItem>) -> i32 {
+    match item.and_then(|i| i.skin_part).map(|p| p.marker()) {
+        Some(marker) if marker > 0 => (marker << 8) | (15 << 20),
+        _ => -1,
+    }
+}
+
+fn push_item(buf: &mut Vec<u8>, item: Option<&DisplayItem>) {
+    push(
+        buf,
+        Metadata::new(
+            BRIGHTNESS_OVERRIDE,
+            MetaDataType::Integer,
+            VarInt(brightness(item)),
+        ),
+    );
+    push(
+        buf,
+        Metadata::new(ITEM, MetaDataType::ItemStack, DisplayItemStack(item)),
+    );
+}
+
+fn push<T: Serialize>(buf: &mut Vec<u8>, metadata: Metadata<T>) {
+    metadata
+        .serialize(&mut Serializer::new(&mut *buf))
+        .expect("metadata serialization into a buffer cannot fail");
+}
+
+fn metadata(
+    entity_id: i32,
+    transform: &DisplayTransform,
+    interpolation_ticks: i32,
+    item: Option<Option<&DisplayItem>>,
+) -> CSetEntityMetadata {
+    let mut buf = Vec::new();
+    push(
+        &mut buf,
+        Metadata::new(INTERPOLATION_DELAY, MetaDataType::Integer, VarInt(0)),
+    );
+    push(
+        &mut buf,
+        Metadata::new(
+            TRANSFORMATION_INTERPOLATION_DURATION,
+            MetaDataType::Integer,
+            VarInt(interpolation_ticks),
+        ),
+    );
+    push(
+        &mut buf,
+        Metadata::new(
+            TELEPORT_DURATION,
+            MetaDataType::Integer,
+            VarInt(interpolation_ticks),
+        ),
+    );
+    push(
+        &mut buf,
+        Metadata::new(TRANSLATION, MetaDataType::Vector3, transform.translation),
+    );
+    push(
+        &mut buf,
+        Metadata::new(SCALE, MetaDataType::Vector3, transform.scale),
+    );
+    let rotation = transform.left_rotation;
+    push(
+        &mut buf,
+        Metadata::new(
+            LEFT_ROTATION,
+            MetaDataType::Quaternion,
+            Quaternion([rotation.x, rotation.y, rotation.z, rotation.w]),
+        ),
+    );
+    if let Some(item) = item {
+        push_item(&mut buf, item);
+    }
+    buf.push(METADATA_END);
+    CSetEntityMetadata::new(VarInt(entity_id), buf.into())
+}
+
+fn item_metadata(entity_id: i32, item: Option<&DisplayItem>) -> CSetEntityMetadata {
+    let mut buf = Vec::new();
+    push_item(&mut buf, item);
+    buf.push(METADATA_END);
+    CSetEntityMetadata::new(VarInt(entity_id), buf.into())
+}
+
+fn hitbox_metadata(entity_id: i32, width: f32, height: f32) -> CSetEntityMetadata {
+    let mut buf = Vec::new();
+    push(
+        &mut buf,
+        Metadata::new(HITBOX_WIDTH, MetaDataType::Float, width),
+    );
+    push(
+        &mut buf,
+        Metadata::new(HITBOX_HEIGHT, MetaDataType::Float, height),
+    );
+    push(
+        &mut buf,
+        Metadata::new(HITBOX_RESPONSE, MetaDataType::Boolean, true),
+    );
+    buf.push(METADATA_END);
+    CSetEntityMetadata::new(VarInt(entity_id), buf.into())
+}
+
+fn spawn_entity(entity_id: i32, entity_type: &EntityType, position: Vector3<f64>) -> CSpawnEntity {
+    CSpawnEntity::new(
+        VarInt(entity_id),
+        uuid::Uuid::new_v4(),
+        VarInt(entity_type.id as i32),
+        position,
+        0.0,
+        0.0,
+        0.0,
+        VarInt(0),
+        Vector3::new(0.0, 0.0, 0.0),
+    )
+}
+
+/// Sends tracker packets to a single player.
+pub async fn send_packets(player: &Player, packets: &[DisplayPacket]) {
+    for packet in packets {
+        match packet {
+            DisplayPacket::Spawn {
+                entity_id,
+                position,
+                item,
+                transform,
+            } => {
+                player
+                    .client
+                    .enqueue_packet(&spawn_entity(
+                        *entity_id,
+                        &EntityType::ITEM_DISPLAY,
+                        *position,
+                    ))
+                    .await;
+                player
+                    .client
+                    .enqueue_packet(&metadata(*entity_id, transform, 0, Some(item.as_ref())))
+                    .await;
+            }
+            DisplayPacket::Item { entity_id, item } => {
+                player
+                    .client
+                    .enqueue_packet(&item_metadata(*entity_id, item.as_ref()))
+                    .await;
+            }
+            DisplayPacket::Transform {
+                entity_id,
+                transform,
+                interpolation_ticks,
+            } => {
+                player
+                    .client
+                    .enqueue_packet(&metadata(*entity_id, transform, *interpolation_ticks, None))
+                    .await;
+            }
+            DisplayPacket::SpawnHitBox {
+                entity_id,
+                position,
+                width,
+                height,
+            } => {
+                player
+                    .client
+                    .enqueue_packet(&spawn_entity(
+                        *entity_id,
+                        &EntityType::INTERACTION,
+                        *position,
+                    ))
+                    .await;
+                player
+                    .client
+                    .enqueue_packet(&hitbox_metadata(*entity_id, *width, *height))
+                    .await;
+            }
+            DisplayPacket::HitBoxSize {
+                entity_id,
+                width,
+                height,
+            } => {
+                player
+                    .client
+                    .enqueue_packet(&hitbox_metadata(*entity_id, *width, *height))
+                    .await;
+            }
+            DisplayPacket::SpawnSeat {
+                entity_id,
+                position,
+            } => {
+                player
+                    .client
+                    .enqueue_packet(&spawn_entity(
+                        *entity_id,
+                        &EntityType::ITEM_DISPLAY,
+                        *position,
+                    ))
+                    .await;
+            }
+            DisplayPacket::Passengers {
+                vehicle,
+                passengers,
+            } => {
+                let passengers = passengers.iter().map(|id| VarInt(*id)).collect::<Vec<_>>();
+                player
+                    .client
+                    .enqueue_packet(&CSetPassengers::new(VarInt(*vehicle), &passengers))
+                    .await;
+            }
+            DisplayPacket::Move {
+                entity_ids,
+                position,
+            } => {
+                for entity_id in entity_ids {
+                    player
+                        .client
+                        .enqueue_packet(&CEntityPositionSync::new(
+                            VarInt(*entity_id),
+                            *position,
+                            Vector3::new(0.0, 0.0, 0.0),
+                            0.0,
+                            0.0,
+                            false,
+                        ))
+                        .await;
+                }
+            }
+            DisplayPacket::Remove { entity_ids } => {
+                let ids = entity_ids.iter().map(|id| VarInt(*id)).collect::<Vec<_>>();
+                player
+                    .client
+                    .enqueue_packet(&CRemoveEntities::new(&ids))
+                    .await;
+            }
+            DisplayPacket::Sound { name, position } => {
+                let Some(sound) = Sound::from_name(strip_namespace(name)) else {
+                    log::warn!("Unknown sound {name} in a model animation");
+                    continue;
+                };
+                player
+                    .play_sound(
+                        sound as u16,
+                        SoundCategory::Neutral,
+                        position,
+                        1.0,
+                        1.0,
+                        0.0,
+                    )
+                    .await;
+            }
+            DisplayPacket::Particle { name, position } => {
+                let Some(particle) = Particle::from_name(strip_namespace(name)) else {
+                    log::warn!("Unknown particle {name} in a model animation");
+                    continue;
+                };
+                player
+                    .client
+                    .enqueue_packet(&CParticle::new(
+                        false,
+                        false,
+                        *position,
+                        Vector3::new(0.0, 0.0, 0.0),
+                        0.0,
+                        1,
+                        VarInt(particle as i32),
+                        &[],
+                    ))
+                    .await;
+            }
+        }
+    }
+}
+
+/// Blockbench effects may be written with or without the `minecraft:` namespace.
+fn strip_namespace(name: &str) -> &str {
+    name.strip_prefix("minecraft:").unwrap_or(name)
+}
+
+/// Items held and worn by `living`, read by the item mapped bones.
+async fn equipment(living: &LivingEntity) -> Equipment {
+    let slots = [
+        (EquipmentSlot::MainHand, PumpkinEquipmentSlot::MAIN_HAND),
+        (EquipmentSlot::OffHand, PumpkinEquipmentSlot::OFF_HAND),
+        (EquipmentSlot::Head, PumpkinEquipmentSlot::HEAD),
+        (EquipmentSlot::Chest, PumpkinEquipmentSlot::CHEST),
+        (EquipmentSlot::Legs, PumpkinEquipmentSlot::LEGS),
+        (EquipmentSlot::Feet, PumpkinEquipmentSlot::FEET),
+    ];
+    let mut equipment = Equipment::new();
+    let entity_equipment = living.entity_equipment.lock().await;
+    for (slot, pumpkin_slot) in slots {
+        let stack = entity_equipment.get(&pumpkin_slot);
+        let stack = stack.lock().await;
+        if stack.is_empty() {
+            continue;
+        }
+        // Composants changeant l'apparence de l'objet
+        let item = DisplayItem {
+            item_model: stack
+                .get_data_component::<ItemModelImpl>()
+                .map(|c| c.model.to_string()),
+            custom_model_data: stack.get_data_component::<CustomModelDataImpl>().map(|c| {
+                CustomModelData {
+                    floats: c.floats.to_vec(),
+                    flags: c.flags.to_vec(),
+                    strings: c.strings.iter().map(|s| s.to_string()).collect(),
+                    colors: c.colors.to_vec(),
+                }
+            }),
+            enchantment_glint: stack
+                .get_data_component::<EnchantmentGlintOverrideImpl>()
+                .map(|c| c.has_glint),
+            dyed_color: stack.get_data_component::<DyedColorImpl>().map(|c| c.color),
+            ..DisplayItem::new(format!("minecraft:{}", stack.item.registry_key))
+        };
+        equipment.set(slot, Some(item));
+    }
+    equipment
+}
+
+fn position(player: &Player) -> Vector3<f64> {
+    player.living_entity.entity.pos.load()
+}
+
+/// Key of the world `entity` is in, models are only shown to players of the same world.
+fn world_key(entity: &Entity) -> String {
+    entity.world.get_world_name().to_string()
+}
+
+async fn send_viewer_packets(server: &Server, out: ViewerPackets) {
+    for (viewer, packets) in out {
+        if let Some(player) = server.get_player_by_uuid(viewer).await {
+            send_packets(&player, &packets).await;
+        }
+    }
+}
+
+/// Entities carrying a model, their location is copied onto the model every tick.
+static ATTACHED_ENTITIES: LazyLock<RwLock<HashMap<i32, Arc<dyn EntityBase>>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Replaces the appearance of `entity` with `model` until `detach` is called.
+pub async fn attach(
+    server: &Server,
+    entity: Arc<dyn EntityBase>,
+    model: Arc<ModelBlueprint>,
+) -> TrackerId {
+    attach_with_skin(server, entity, model, None).await
+}
+
+/// Like `attach`, the player part bones of `model` are drawn with the skin of `player`.
+pub async fn attach_player(
+    server: &Server,
+    player: Arc<Player>,
+    model: Arc<ModelBlueprint>,
+) -> TrackerId {
+    let skin = skin_profile(&player);
+    attach_with_skin(server, player, model, skin).await
+}
+
+fn skin_profile(player: &Player) -> Option<SkinProfile> {
+    let profile = &player.gameprofile;
+    let textures = profile.properties.iter().find(|p| p.name == "textures")?;
+    Some(SkinProfile {
+        id: profile.id,
+        name: profile.name.clone(),
+        textures: textures.value.clone(),
+        signature: textures.signature.clone(),
+    })
+}
+
+async fn attach_with_skin(
+    server: &Server,
+    entity: Arc<dyn EntityBase>,
+    model: Arc<ModelBlueprint>,
+    skin: Option<SkinProfile>,
+) -> TrackerId {
+    let base = entity.get_entity();
+    let mut tracker = ModelTracker::new(model, world_key(base), base.pos.load());
+    // Pas encore affiché, aucun paquet à envoyer
+    tracker.set_skin(skin, &mut Vec::new());
+    tracker.set_body_yaw(base.yaw.load());
+    tracker.set_head_rotation(base.pitch.load(), base.head_yaw.load());
+    let mut out = ViewerPackets::new();
+    let id = get_tracker_registry()
+        .write()
+        .unwrap()
+        .attach(base.entity_id, tracker, &mut out);
+    ATTACHED_ENTITIES
+        .write()
+        .unwrap()
+        .insert(base.entity_id, entity.clone());
+    base.set_invisible(true).await;
+    send_viewer_packets(server, out).await;
+    id
+}
+
+/// Removes the model of `entity` and makes it visible again.
+pub async fn detach(server: &Server, entity: &dyn EntityBase) -> Option<ModelTracker> {
+    let base = entity.get_entity();
+    ATTACHED_ENTITIES.write().unwrap().remove(&base.entity_id);
+    let mut out = ViewerPackets::new();
+    let (tracker, events) = {
+        let registry = get_tracker_registry();
+        let mut registry = registry.write().unwrap();
+        let tracker = registry.detach(base.entity_id, &mut out);
+        (tracker, registry.take_seat_events())
+    };
+    dispatch(events);
+    if tracker.is_some() {
+        base.set_invisible(false).await;
+    }
+    send_viewer_packets(server, out).await;
+    tracker
+}
+
+/// Whether the model of `entity` must be removed because it no longer exists in its world.
+fn is_gone(entity: &dyn EntityBase) -> bool {
+    entity.get_entity().removed.load(Ordering::Relaxed)
+        || entity
+            .get_living_entity()
+            .is_some_and(|living| living.dead.load(Ordering::Relaxed))
+}
+
+/// Shows the models around a player as soon as they join.
+pub struct TrackerJoinHandler;
+
+#[with_runtime(global)]
+#[async_trait]
+impl EventHandler<PlayerJoinEvent> for TrackerJoinHandler {
+    async fn handle(&self, _server: &Arc<Server>, event: &PlayerJoinEvent) {
+        let player = &event.player;
+        let mut out = ViewerPackets::new();
+        get_tracker_registry().write().unwrap().join(
+            player.gameprofile.id,
+            &world_key(&player.living_entity.entity),
+            position(player),
+            &mut out,
+        );
+        if let Some(packets) = out.get(&player.gameprofile.id) {
+            send_packets(player, packets).await;
+        }
+    }
+}
+
+pub struct TrackerLeaveHandler;
+
+#[with_runtime(global)]
+#[async_trait]
+impl EventHandler<PlayerLeaveEvent> for TrackerLeaveHandler {
+    async fn handle(&self, server: &Arc<Server>, event: &PlayerLeaveEvent) {
+        let entity_id = event.player.entity_id();
+        ATTACHED_ENTITIES.write().unwrap().remove(&entity_id);
+        let mut out = ViewerPackets::new();
+        let events = {
+            let registry = get_tracker_registry();
+            let mut registry = registry.write().unwrap();
+            registry.detach(entity_id, &mut out);
+            registry.leave(&event.player.gameprofile.id, &mut out);
+            registry.take_seat_events()
+        };
+        dispatch(events);
+        send_viewer_packets(server, out).await;
+    }
+}
+
+/// Forwards a click of `player` on a hitbox interaction entity to the model owning it.
+/// Interaction entities only exist client side, so this is called with the entity id
+/// of the interact packet the server could not resolve.
+pub fn hit(player: &Player, entity_id: i32, kind: HitKind) -> Option<TrackerId> {
+    let (id, event) =
+        get_tracker_registry()
+            .read()
+            .unwrap()
+            .hit(entity_id, player.gameprofile.id, kind)?;
+    // Le verrou est relâché, un listener peut modifier le registre
+    event.dispatch();
+    Some(id)
+}
+
+/// Routes the clicks on the hitboxes of the models, the server doesn't know these entities.
+pub struct TrackerInteractHandler;
+
+#[with_runtime(global)]
+#[async_trait]
+impl EventHandler<PlayerInteractUnknownEntityEvent> for TrackerInteractHandler {
+    async fn handle(&self, server: &Arc<Server>, event: &PlayerInteractUnknownEntityEvent) {
+        let kind = match event.action {
+            ActionType::Attack => HitKind::Attack,
+            ActionType::Interact => HitKind::Interact,
+            // Suivi d'un `Interact`, ne compte qu'une fois
+            ActionType::InteractAt => return,
+        };
+        hit(&event.player, event.entity_id, kind);
+        if kind != HitKind::Interact {
+            return;
+        }
+        // Un clic droit sur un modèle assoit le joueur sur la première place libre
+        let Some(id) = get_tracker_registry()
+            .read()
+            .unwrap()
+            .owner(event.entity_id)
+        else {
+            return;
+        };
+        match mount(server, &event.player, id, None).await {
+            Ok(_) | Err(SeatError::NoFreeSeat) => {}
+            Err(e) => log::debug!("Failed to seat {}: {}", event.player.gameprofile.name, e),
+        }
+    }
+}
+
+/// Steers the model ridden by the player, the sneak key is part of the input and dismounts.
+pub struct TrackerInputHandler;
+
+#[with_runtime(global)]
+#[async_trait]
+impl EventHandler<PlayerInputEvent> for TrackerInputHandler {
+    async fn handle(&self, server: &Arc<Server>, event: &PlayerInputEvent) {
+        steer(server, &event.player, player_input(event.input)).await;
+    }
+}
+
+// Bits du paquet player input
+const INPUT_FORWARD: u8 = 0x01;
+const INPUT_BACKWARD: u8 = 0x02;
+const INPUT_LEFT: u8 = 0x04;
+const INPUT_RIGHT: u8 = 0x08;
+const INPUT_JUMP: u8 = 0x10;
+const INPUT_SNEAK: u8 = 0x20;
+
+fn player_input(flags: u8) -> PlayerInput {
+    let axis = |positive: u8, negative: u8| {
+        (flags & positive != 0) as i8 as f32 - (flags & negative != 0) as i8 as f32
+    };
+    PlayerInput {
+        forward: axis(INPUT_FORWARD, INPUT_BACKWARD),
+        sideways: axis(INPUT_LEFT, INPUT_RIGHT),
+        jump: flags & INPUT_JUMP != 0,
+        sneak: flags & INPUT_SNEAK != 0,
+    }
+}
+
+/// Calls the listeners of `events`, once the tracker registry lock is released.
+fn dispatch<E>(events: Vec<PendingEvent<E>>) {
+    for event in events {
+        event.dispatch();
+    }
+}
+
+/// Seats `player` on `bone` of a model, or on its first free seat.
+pub async fn mount(
+    server: &Server,
+    player: &Player,
+    id: TrackerId,
+    bone: Option<&BoneName>,
+) -> Result<BoneName, SeatError> {
+    let mut out = ViewerPackets::new();
+    let (result, events) = {
+        let registry = get_tracker_registry();
+        let mut registry = registry.write().unwrap();
+        let result = registry.mount(
+            id,
+            bone,
+            player.gameprofile.id,
+            player.entity_id(),
+            &mut out,
+        );
+        (result, registry.take_seat_events())
+    };
+    dispatch(events);
+    send_viewer_packets(server, out).await;
+    result
+}
+
+pub async fn dismount(server: &Server, player: &Player) -> Option<TrackerId> {
+    let mut out = ViewerPackets::new();
+    let (id, events) = {
+        let registry = get_tracker_registry();
+        let mut registry = registry.write().unwrap();
+        let id = registry.dismount(&player.gameprofile.id, &mut out);
+        (id, registry.take_seat_events())
+    };
+    dispatch(events);
+    send_viewer_packets(server, out).await;
+    id
+}
+
+/// Forwards the movement keys of a driver to the entity carrying the model, sneaking dismounts.
+pub async fn steer(server: &Server, player: &Player, input: PlayerInput) {
+    if input.sneak {
+        dismount(server, player).await;
+        return;
+    }
+    let Some(entity_id) = get_tracker_registry()
+        .read()
+        .unwrap()
+        .steered_entity(&player.gameprofile.id)
+    else {
+        return;
+    };
+    let Some(entity) = ATTACHED_ENTITIES.read().unwrap().get(&entity_id).cloned() else {
+        return;
+    };
+    let base = entity.get_entity();
+    let yaw = player.living_entity.entity.yaw.load();
+    base.yaw.store(yaw);
+    base.head_yaw.store(yaw);
+    let mut velocity = input.velocity(yaw);
+    velocity.y = base.velocity.load().y;
+    base.set_velocity(velocity).await;
+}
+
+/// Updates the viewers of every model then sends the bone changes.
+pub async fn tick(server: &Server) {
+    let players = server.get_all_players().await;
+    let (entities, gone): (Vec<_>, Vec<_>) = {
+        let mut attached = ATTACHED_ENTITIES.write().unwrap();
+        // Les entités mortes ou retirées du monde perdent leur modèle
+        let gone = attached
+            .iter()
+            .filter(|(_, entity)| is_gone(entity.as_ref()))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for entity_id in &gone {
+            attached.remove(entity_id);
+        }
+        (attached.values().cloned().collect(), gone)
+    };
+    let mut equipments = Vec::new();
+    for entity in &entities {
+        if let Some(living) = entity.get_living_entity() {
+            equipments.push((living.entity.entity_id, equipment(living).await));
+        }
+    }
+    let mut out = ViewerPackets::new();
+    let (commands, events, transitions) = {
+        let registry = get_tracker_registry();
+        let mut registry = registry.write().unwrap();
+        for entity_id in gone {
+            registry.detach(entity_id, &mut out);
+        }
+        for entity in &entities {
+            let base = entity.get_entity();
+            registry.follow(
+                base.entity_id,
+                &world_key(base),
+                base.pos.load(),
+                base.yaw.load(),
+                base.pitch.load(),
+                base.head_yaw.load(),
+            );
+        }
+        for (entity_id, equipment) in equipments {
+            registry.equip(entity_id, equipment);
+        }
+        for player in &players {
+            registry.move_viewer(
+                player.gameprofile.id,
+                &world_key(&player.living_entity.entity),
+                position(player),
+                &mut out,
+            );
+        }
+        registry.tick(&mut out);
+        (
+            registry.take_commands(),
+            registry.take_seat_events(),
+            registry.take_transition_events(),
+        )
+    };
+    dispatch(events);
+    dispatch(transitions);
+    for player in &players {
+        if let Some(packets) = out.get(&player.gameprofile.id) {
+            send_packets(player, packets).await;
+        }
+    }
+    let dispatcher = server.command_dispatcher.read().await;
+    for command in commands {
+        dispatcher
+            .handle_command(&mut CommandSender::Console, server, &command)
+            .await;
+    }
+}
+
+pub fn start_ticking(server: Arc<Server>) {
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(Duration::from_millis(DEFAULT_UPDATE_INTERVAL_MILIS as u64));
+        loop {
+            interval.tick().await;
+            tick(&server).await;
+        }
+    });
+}
@@ -0,0 +1,622 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::RwLock;
+
+use pumpkin_util::math::vector3::Vector3;
+use uuid::Uuid;
+
+use crate::bone::BoneName;
+use crate::bone::item::Equipment;
+
+use super::DisplayPacket;
+use super::PendingEvent;
+use super::animation::TransitionEvent;
+use super::hitbox::HitEvent;
+use super::hitbox::HitKind;
+use super::seat::SeatError;
+use super::seat::SeatEvent;
+use super::tracker::ModelTracker;
+
+/// Distance in blocks under which a player receives the bones of a model.
+pub const DEFAULT_VIEW_DISTANCE: f64 = 64.0;
+
+pub type TrackerId = u32;
+
+/// Packets to send, grouped by viewer.
+pub type ViewerPackets = HashMap<Uuid, Vec<DisplayPacket>>;
+
+struct TrackedModel {
+    tracker: ModelTracker,
+    viewers: HashSet<Uuid>,
+}
+
+/// Every spawned model instance and the players currently seeing it.
+pub struct TrackerRegistry {
+    view_distance: f64,
+    trackers: HashMap<TrackerId, TrackedModel>,
+    /// World and position of every player
+    viewers: HashMap<Uuid, (String, Vector3<f64>)>,
+    /// Models following a server entity, by entity id
+    attached: HashMap<i32, TrackerId>,
+    /// Seat changes of the removed models, not yet taken
+    seat_events: Vec<PendingEvent<SeatEvent>>,
+    /// Transition changes of the removed models, not yet taken
+    transition_events: Vec<PendingEvent<TransitionEvent>>,
+    next_id: TrackerId,
+}
+
+impl TrackerRegistry {
+    pub fn new(view_distance: f64) -> Self {
+        Self {
+            view_distance,
+            trackers: HashMap::new(),
+            viewers: HashMap::new(),
+            attached: HashMap::new(),
+            seat_events: Vec::new(),
+            transition_events: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn view_distance(&self) -> f64 {
+        self.view_distance
+    }
+
+    /// Takes effect on the next `move_viewer` of each player.
+    pub fn set_view_distance(&mut self, view_distance: f64) {
+        self.view_distance = view_distance;
+    }
+
+    pub fn len(&self) -> usize {
+        self.trackers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trackers.is_empty()
+    }
+
+    pub fn get(&self, id: TrackerId) -> Option<&ModelTracker> {
+        self.trackers.get(&id).map(|m| &m.tracker)
+    }
+
+    pub fn get_mut(&mut self, id: TrackerId) -> Option<&mut ModelTracker> {
+        self.trackers.get_mut(&id).map(|m| &mut m.tracker)
+    }
+
+    pub fn viewers(&self, id: TrackerId) -> Option<&HashSet<Uuid>> {
+        self.trackers.get(&id).map(|m| &m.viewers)
+    }
+
+    fn in_range(
+        view_distance: f64,
+        tracker: &ModelTracker,
+        world: &str,
+        position: &Vector3<f64>,
+    ) -> bool {
+        tracker.world() == world
+            && tracker.position().sub(position).length_squared() <= view_distance * view_distance
+    }
+
+    /// Spawns `tracker` for every known player in range.
+    pub fn add(&mut self, mut tracker: ModelTracker, out: &mut ViewerPackets) -> TrackerId {
+        let mut packets = Vec::new();
+        tracker.spawn(&mut packets);
+        let viewers = self
+            .viewers
+            .iter()
+            .filter(|(_, (world, position))| {
+                Self::in_range(self.view_distance, &tracker, world, position)
+            })
+            .map(|(viewer, _)| *viewer)
+            .collect::<HashSet<_>>();
+        for viewer in &viewers {
+            out.entry(*viewer)
+                .or_default()
+                .extend(packets.iter().cloned());
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.trackers.insert(id, TrackedModel { tracker, viewers });
+        id
+    }
+
+    pub fn remove(&mut self, id: TrackerId, out: &mut ViewerPackets) -> Option<ModelTracker> {
+        let TrackedModel {
+            mut tracker,
+            viewers,
+        } = self.trackers.remove(&id)?;
+        self.attached.retain(|_, tracker| *tracker != id);
+        let mut packets = Vec::new();
+        tracker.despawn(&mut packets);
+        self.seat_events.extend(tracker.take_seat_events());
+        self.transition_events
+            .extend(tracker.take_transition_events());
+        for viewer in viewers {
+            out.entry(viewer)
+                .or_default()
+                .extend(packets.iter().cloned());
+        }
+        Some(tracker)
+    }
+
+    /// Spawns `tracker` on top of an entity, replacing any model already attached to it.
+    pub fn attach(
+        &mut self,
+        entity_id: i32,
+        tracker: ModelTracker,
+        out: &mut ViewerPackets,
+    ) -> TrackerId {
+        self.detach(entity_id, out);
+        let id = self.add(tracker, out);
+        self.attached.insert(entity_id, id);
+        id
+    }
+
+    pub fn detach(&mut self, entity_id: i32, out: &mut ViewerPackets) -> Option<ModelTracker> {
+        let id = self.attached.remove(&entity_id)?;
+        self.remove(id, out)
+    }
+
+    pub fn attached(&self, entity_id: i32) -> Option<TrackerId> {
+        self.attached.get(&entity_id).copied()
+    }
+
+    pub fn attached_entities(&self) -> Vec<i32> {
+        self.attached.keys().copied().collect()
+    }
+
+    /// Model owning the display, hitbox or seat entity `entity_id`.
+    pub fn owner(&self, entity_id: i32) -> Option<TrackerId> {
+        self.trackers
+            .iter()
+            .find(|(_, model)| model.tracker.entity_ids().contains(&entity_id))
+            .map(|(id, _)| *id)
+    }
+
+    /// Copies the location of an entity onto its model, sent on the next `tick`.
+    pub fn follow(
+        &mut self,
+        entity_id: i32,
+        world: &str,
+        position: Vector3<f64>,
+        body_yaw: f32,
+        head_pitch: f32,
+        head_yaw: f32,
+    ) {
+        let Some(tracker) = self
+            .attached
+            .get(&entity_id)
+            .and_then(|id| self.trackers.get_mut(id))
+            .map(|m| &mut m.tracker)
+        else {
+            return;
+        };
+        if tracker.world() != world {
+            tracker.set_world(world);
+        }
+        tracker.set_position(position);
+        tracker.set_body_yaw(body_yaw);
+        tracker.set_head_rotation(head_pitch, head_yaw);
+    }
+
+    /// Copies the items of an entity onto its model, the changed item mapped bones are sent on the next `tick`.
+    pub fn equip(&mut self, entity_id: i32, equipment: Equipment) {
+        if let Some(model) = self
+            .attached
+            .get(&entity_id)
+            .and_then(|id| self.trackers.get_mut(id))
+        {
+            model.tracker.set_equipment(equipment);
+        }
+    }
+
+    /// Shows or hides a bone of the model `id`, see `ModelTracker::set_visible`.
+    pub fn set_visible(&mut self, id: TrackerId, bone: &BoneName, visible: bool, recursive: bool) {
+        if let Some(model) = self.trackers.get_mut(&id) {
+            model.tracker.set_visible(bone, visible, recursive);
+        }
+    }
+
+    /// Finds the model owning the hitbox entity, its listeners are called by the caller once the lock is released.
+    pub fn hit(
+        &self,
+        entity_id: i32,
+        player: Uuid,
+        kind: HitKind,
+    ) -> Option<(TrackerId, PendingEvent<HitEvent>)> {
+        self.trackers.iter().find_map(|(id, model)| {
+            let bone = model.tracker.hitbox_bone(entity_id)?;
+            let event = model.tracker.hit(HitEvent {
+                bone: bone.clone(),
+                player,
+                kind,
+            });
+            Some((*id, event))
+        })
+    }
+
+    pub fn join(
+        &mut self,
+        viewer: Uuid,
+        world: &str,
+        position: Vector3<f64>,
+        out: &mut ViewerPackets,
+    ) {
+        self.move_viewer(viewer, world, position, out);
+    }
+
+    /// Forgets the player, only the other viewers of their seat are notified since the connection is gone.
+    pub fn leave(&mut self, viewer: &Uuid, out: &mut ViewerPackets) {
+        self.dismount(viewer, out);
+        out.remove(viewer);
+        self.viewers.remove(viewer);
+        for model in self.trackers.values_mut() {
+            model.viewers.remove(viewer);
+        }
+    }
+
+    fn broadcast(viewers: &HashSet<Uuid>, packets: &[DisplayPacket], out: &mut ViewerPackets) {
+        if packets.is_empty() {
+            return;
+        }
+        for viewer in viewers {
+            out.entry(*viewer)
+                .or_default()
+                .extend(packets.iter().cloned());
+        }
+    }
+
+    /// Seats `player` on `bone`, or on the first free seat, after leaving any other seat.
+    pub fn mount(
+        &mut self,
+        id: TrackerId,
+        bone: Option<&BoneName>,
+        player: Uuid,
+        entity_id: i32,
+        out: &mut ViewerPackets,
+    ) -> Result<BoneName, SeatError> {
+        let model = self.trackers.get(&id).ok_or(SeatError::UnknownModel)?;
+        let bone = match bone {
+            Some(bone) => bone.clone(),
+            None => model
+                .tracker
+                .free_seat()
+                .cloned()
+                .ok_or(SeatError::NoFreeSeat)?,
+        };
+        match model
+            .tracker
+            .seats()
+            .into_iter()
+            .find(|(b, _, _)| **b == bone)
+        {
+            None => return Err(SeatError::NotASeat(bone.name().to_string())),
+            Some((_, _, Some(p))) if p == player => return Ok(bone),
+            Some((_, _, Some(_))) => return Err(SeatError::Occupied(bone.name().to_string())),
+            Some((_, _, None)) => {}
+        }
+        self.dismount(&player, out);
+        let model = self.trackers.get_mut(&id).ok_or(SeatError::UnknownModel)?;
+        let mut packets = Vec::new();
+        model
+            .tracker
+            .mount(&bone, player, entity_id, &mut packets)?;
+        Self::broadcast(&model.viewers, &packets, out);
+        Ok(bone)
+    }
+
+    /// Takes `player` off the model they ride, if any.
+    pub fn dismount(&mut self, player: &Uuid, out: &mut ViewerPackets) -> Option<TrackerId> {
+        self.trackers.iter_mut().find_map(|(id, model)| {
+            let mut packets = Vec::new();
+            model.tracker.dismount(player, &mut packets)?;
+            Self::broadcast(&model.viewers, &packets, out);
+            Some(*id)
+        })
+    }
+
+    pub fn seated(&self, player: &Uuid) -> Option<(TrackerId, &BoneName)> {
+        self.trackers
+            .iter()
+            .find_map(|(id, model)| Some((*id, model.tracker.seat_of(player)?)))
+    }
+
+    /// Entity the driver steers, when `player` is on the primary seat of an attached model.
+    pub fn steered_entity(&self, player: &Uuid) -> Option<i32> {
+        self.attached.iter().find_map(|(entity_id, id)| {
+            let model = self.trackers.get(id)?;
+            (model.tracker.driver() == Some(*player)).then_some(*entity_id)
+        })
+    }
+
+    /// Shows the models that entered the view distance of the player and hides the ones that left it,
+    /// models of another world are never in range.
+    pub fn move_viewer(
+        &mut self,
+        viewer: Uuid,
+        world: &str,
+        position: Vector3<f64>,
+        out: &mut ViewerPackets,
+    ) {
+        for model in self.trackers.values_mut() {
+            let visible = Self::in_range(self.view_distance, &model.tracker, world, &position);
+            if visible == model.viewers.contains(&viewer) {
+                continue;
+            }
+            let packets = out.entry(viewer).or_default();
+            if visible {
+                model.tracker.show(packets);
+                model.viewers.insert(viewer);
+            } else {
+                model.tracker.hide(packets);
+                model.viewers.remove(&viewer);
+            }
+        }
+        self.viewers.insert(viewer, (world.to_string(), position));
+    }
+
+    /// Ticks every tracker and sends its changes to its viewers only.
+    pub fn tick(&mut self, out: &mut ViewerPackets) {
+        for model in self.trackers.values_mut() {
+            let mut packets = Vec::new();
+            model.tracker.tick(&mut packets);
+            Self::broadcast(&model.viewers, &packets, out);
+        }
+    }
+
+    /// Mounts and dismounts of every tracker since the last call, to dispatch once the lock is released.
+    pub fn take_seat_events(&mut self) -> Vec<PendingEvent<SeatEvent>> {
+        let mut events = std::mem::take(&mut self.seat_events);
+        for model in self.trackers.values_mut() {
+            events.extend(model.tracker.take_seat_events());
+        }
+        events
+    }
+
+    /// Animation transitions of every tracker since the last call, to dispatch once the lock is released.
+    pub fn take_transition_events(&mut self) -> Vec<PendingEvent<TransitionEvent>> {
+        let mut events = std::mem::take(&mut self.transition_events);
+        for model in self.trackers.values_mut() {
+            events.extend(model.tracker.take_transition_events());
+        }
+        events
+    }
+
+    /// Commands run by the animation scripts of every tracker since the last call.
+    pub fn take_commands(&mut self) -> Vec<String> {
+        self.trackers
+            .values_mut()
+            .flat_map(|model| model.tracker.take_commands())
+            .collect()
+    }
+}
+
+impl Default for TrackerRegistry {
+    fn default() -> Self {
+        Self::new(DEFAULT_VIEW_DISTANCE)
+    }
+}
+
+pub static TRACKER_REGISTRY: LazyLock<Arc<RwLock<TrackerRegistry>>> =
+    LazyLock::new(|| Arc::new(RwLock::new(TrackerRegistry::default())));
+
+pub fn get_tracker_registry() -> Arc<RwLock<TrackerRegistry>> {
+    TRACKER_REGISTRY.clone()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use pumpkin_util::math::vector3::Vector3;
+    use uuid::Uuid;
+
+    use crate::bone::BoneTag;
+    use crate::data::fixture::blueprint;
+    use crate::data::fixture::bone;
+    use crate::data::fixture::load;
+    use crate::data::fixture::tag;
+    use crate::render::BonePose;
+    use crate::render::DisplayPacket;
+    use crate::render::tracker::ModelTracker;
+
+    use crate::render::seat::SeatError;
+
+    use super::TrackerRegistry;
+    use super::ViewerPackets;
+
+    fn tracker() -> ModelTracker {
+        ModelTracker::new(blueprint(), "overworld", Vector3::new(0.0, 64.0, 0.0))
+    }
+
+    #[test]
+    fn test_view_distance() {
+        let mut registry = TrackerRegistry::new(16.0);
+        let (near, far) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut out = ViewerPackets::new();
+        registry.join(near, "overworld", Vector3::new(4.0, 64.0, 0.0), &mut out);
+        registry.join(far, "overworld", Vector3::new(100.0, 64.0, 0.0), &mut out);
+        let id = registry.add(tracker(), &mut out);
+        assert_eq!(out[&near].len(), 4);
+        assert!(!out.contains_key(&far));
+
+        out.clear();
+        registry.move_viewer(far, "overworld", Vector3::new(0.0, 64.0, 10.0), &mut out);
+        registry.move_viewer(near, "overworld", Vector3::new(0.0, 64.0, -40.0), &mut out);
+        assert!(matches!(
+            out[&near].as_slice(),
+            [DisplayPacket::Remove { .. }]
+        ));
+        assert_eq!(out[&far].len(), 4);
+        assert_eq!(registry.viewers(id).unwrap(), &HashSet::from([far]));
+    }
+
+    #[test]
+    fn test_other_world() {
+        let mut registry = TrackerRegistry::new(16.0);
+        let (overworld, nether) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut out = ViewerPackets::new();
+        registry.join(
+            overworld,
+            "overworld",
+            Vector3::new(0.0, 64.0, 0.0),
+            &mut out,
+        );
+        registry.join(nether, "the_nether", Vector3::new(0.0, 64.0, 0.0), &mut out);
+        let id = registry.add(tracker(), &mut out);
+        assert!(out.contains_key(&overworld));
+        assert!(!out.contains_key(&nether));
+
+        out.clear();
+        registry.move_viewer(
+            overworld,
+            "the_nether",
+            Vector3::new(0.0, 64.0, 0.0),
+            &mut out,
+        );
+        assert!(matches!(
+            out[&overworld].as_slice(),
+            [DisplayPacket::Remove { .. }]
+        ));
+        assert!(registry.viewers(id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_late_joiner_gets_current_state() {
+        let mut registry = TrackerRegistry::new(16.0);
+        let first = Uuid::new_v4();
+        let mut out = ViewerPackets::new();
+        registry.join(first, "overworld", Vector3::new(0.0, 64.0, 0.0), &mut out);
+        let id = registry.add(tracker(), &mut out);
+
+        registry.get_mut(id).unwrap().set_pose(
+            &bone("bone2"),
+            BonePose {
+                rotation: Vector3::new(0.0, 0.0, 45.0),
+                ..BonePose::EMPTY
+            },
+        );
+        out.clear();
+        registry.tick(&mut out);
+        let moved = out[&first]
+            .iter()
+            .map(|p| match p {
+                DisplayPacket::Transform {
+                    entity_id,
+                    transform,
+                    ..
+                } => (*entity_id, *transform),
+                _ => panic!("unexpected packet {p:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(moved.len(), 2);
+
+        let late = Uuid::new_v4();
+        out.clear();
+        registry.join(late, "overworld", Vector3::new(0.0, 64.0, 0.0), &mut out);
+        for (id, transform) in moved {
+            assert!(out[&late].iter().any(|p| matches!(
+                p,
+                DisplayPacket::Spawn { entity_id, transform: t, .. } if *entity_id == id && *t == transform
+            )));
+        }
+
+        registry.leave(&late, &mut out);
+        assert_eq!(registry.viewers(id).unwrap(), &HashSet::from([first]));
+    }
+
+    #[test]
+    fn test_attach_follow_detach() {
+        let mut registry = TrackerRegistry::new(16.0);
+        let viewer = Uuid::new_v4();
+        let mut out = ViewerPackets::new();
+        registry.join(viewer, "overworld", Vector3::new(0.0, 64.0, 0.0), &mut out);
+        let id = registry.attach(7, tracker(), &mut out);
+        assert_eq!(registry.attached(7), Some(id));
+
+        out.clear();
+        registry.follow(7, "overworld", Vector3::new(1.0, 64.0, 1.0), 0.0, 0.0, 0.0);
+        registry.tick(&mut out);
+        assert!(matches!(out[&viewer][0], DisplayPacket::Move { .. }));
+
+        out.clear();
+        assert!(registry.detach(7, &mut out).is_some());
+        assert!(matches!(
+            out[&viewer].as_slice(),
+            [DisplayPacket::Remove { .. }]
+        ));
+        assert!(registry.is_empty());
+        assert_eq!(registry.attached(7), None);
+    }
+
+    #[test]
+    fn test_mount() {
+        let mut model = load();
+        tag(&mut model.group, "bone2", BoneTag::SEAT);
+        tag(&mut model.group, "bone4", BoneTag::SUB_SEAT);
+        let mut tracker =
+            ModelTracker::new(Arc::new(model), "overworld", Vector3::new(0.0, 64.0, 0.0));
+        let events = Arc::new(AtomicUsize::new(0));
+        let counter = events.clone();
+        tracker.add_seat_listener(Arc::new(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        let mut registry = TrackerRegistry::new(16.0);
+        let (driver, passenger) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut out = ViewerPackets::new();
+        registry.join(driver, "overworld", Vector3::new(0.0, 64.0, 0.0), &mut out);
+        registry.join(
+            passenger,
+            "overworld",
+            Vector3::new(0.0, 64.0, 0.0),
+            &mut out,
+        );
+        let id = registry.attach(3, tracker, &mut out);
+
+        out.clear();
+        let seat = registry.mount(id, None, driver, 100, &mut out).unwrap();
+        assert_eq!(seat.name(), "bone2");
+        assert!(matches!(
+            out[&passenger].as_slice(),
+            [DisplayPacket::Passengers { passengers, .. }] if passengers == &[100]
+        ));
+        assert_eq!(
+            registry.mount(id, Some(&seat), passenger, 101, &mut out),
+            Err(SeatError::Occupied("bone2".to_string()))
+        );
+        assert_eq!(
+            registry
+                .mount(id, None, passenger, 101, &mut out)
+                .unwrap()
+                .name(),
+            "bone4"
+        );
+        assert_eq!(registry.steered_entity(&driver), Some(3));
+        assert_eq!(registry.steered_entity(&passenger), None);
+        let DisplayPacket::Passengers { vehicle, .. } = out[&passenger][0] else {
+            panic!("no passengers packet");
+        };
+        assert_eq!(registry.owner(vehicle), Some(id));
+        // Les listeners attendent que l'appelant relâche le verrou
+        assert_eq!(events.load(Ordering::Relaxed), 0);
+        for event in registry.take_seat_events() {
+            event.dispatch();
+        }
+        assert_eq!(events.load(Ordering::Relaxed), 2);
+
+        out.clear();
+        registry.leave(&driver, &mut out);
+        assert!(!out.contains_key(&driver));
+        assert!(matches!(
+            out[&passenger].as_slice(),
+            [DisplayPacket::Passengers { passengers, .. }] if passengers.is_empty()
+        ));
+        assert_eq!(registry.seated(&driver), None);
+    }
+}
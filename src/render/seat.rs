@@ -0,0 +1,105 @@
+use std::fmt::Display;
+
+use pumpkin_util::math::vector3::Vector3;
+use uuid::Uuid;
+
+use crate::bone::BoneName;
+
+use super::Listener;
+
+/// Horizontal speed given to the owner entity by a full movement input, in blocks per tick.
+pub const STEER_SPEED: f64 = 0.2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeatKind {
+    /// `seat` bone, its passenger drives the model
+    Primary,
+    /// `sub_seat` bone, passenger only
+    Sub,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SeatEvent {
+    Mount {
+        bone: BoneName,
+        player: Uuid,
+        kind: SeatKind,
+    },
+    Dismount {
+        bone: BoneName,
+        player: Uuid,
+        kind: SeatKind,
+    },
+}
+
+pub type SeatListener = Listener<SeatEvent>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SeatError {
+    NotASeat(String),
+    Occupied(String),
+    NoFreeSeat,
+    UnknownModel,
+}
+
+impl Display for SeatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SeatError::NotASeat(bone) => write!(f, "bone {} is not a seat", bone),
+            SeatError::Occupied(bone) => write!(f, "seat {} is already occupied", bone),
+            SeatError::NoFreeSeat => write!(f, "every seat is occupied"),
+            SeatError::UnknownModel => write!(f, "the model is not spawned"),
+        }
+    }
+}
+
+impl std::error::Error for SeatError {}
+
+/// Movement keys pressed by the driver of a model.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlayerInput {
+    /// 1 forward, -1 backward
+    pub forward: f32,
+    /// 1 left, -1 right
+    pub sideways: f32,
+    pub jump: bool,
+    pub sneak: bool,
+}
+
+impl PlayerInput {
+    /// Horizontal velocity for a driver looking at `yaw`, in Minecraft degrees.
+    pub fn velocity(&self, yaw: f32) -> Vector3<f64> {
+        let (sin, cos) = (yaw as f64).to_radians().sin_cos();
+        let (forward, sideways) = (self.forward as f64, self.sideways as f64);
+        let length = (forward * forward + sideways * sideways).sqrt();
+        if length < f64::EPSILON {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        let scale = STEER_SPEED / length.max(1.0);
+        Vector3::new(
+            (sideways * cos - forward * sin) * scale,
+            0.0,
+            (forward * cos + sideways * sin) * scale,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PlayerInput;
+    use super::STEER_SPEED;
+
+    #[test]
+    fn test_velocity() {
+        let input = PlayerInput {
+            forward: 1.0,
+            ..Default::default()
+        };
+        // yaw 0 regarde vers +z, yaw 90 vers -x
+        let south = input.velocity(0.0);
+        assert!((south.z - STEER_SPEED).abs() < 1e-9 && south.x.abs() < 1e-9);
+        let west = input.velocity(90.0);
+        assert!((west.x + STEER_SPEED).abs() < 1e-9 && west.z.abs() < 1e-9);
+        assert_eq!(PlayerInput::default().velocity(45.0).x, 0.0);
+    }
+}
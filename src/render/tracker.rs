@@ -0,0 +1,1306 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use pumpkin_util::math::vector3::Vector3;
+use uuid::Uuid;
+
+use crate::bone::BoneItemMapper;
+use crate::bone::BoneName;
+use crate::bone::BoneTag;
+use crate::bone::item;
+use crate::bone::item::Equipment;
+use crate::bone::skin::SkinPart;
+use crate::data::blueprint::BlueprintGroup;
+use crate::data::blueprint::ModelBlueprint;
+use crate::data::blueprint::script::RenderSource;
+use crate::data::blueprint::script::ScriptEffect;
+use crate::pack::PackedBone;
+use crate::pack::packed_bone;
+use crate::pack::skin_item_model;
+use crate::utils::math;
+use crate::utils::math::Quaternion;
+
+use super::BonePose;
+use super::DisplayItem;
+use super::DisplayPacket;
+use super::DisplayTransform;
+use super::PacketSink;
+use super::PendingEvent;
+use super::SkinProfile;
+use super::animation::AnimationController;
+use super::animation::TransitionEvent;
+use super::hitbox::HitBoxBounds;
+use super::hitbox::HitEvent;
+use super::hitbox::HitListener;
+use super::hitbox::WorldHitBox;
+use super::hitbox::is_hitbox;
+use super::hitbox::is_oriented;
+use super::next_entity_id;
+use super::seat::SeatError;
+use super::seat::SeatEvent;
+use super::seat::SeatKind;
+use super::seat::SeatListener;
+
+pub const DEFAULT_UPDATE_INTERVAL_MILIS: u32 = math::MINECRAFT_TICK_MILIS as u32;
+
+struct Seat {
+    entity_id: i32,
+    kind: SeatKind,
+    /// Player riding the seat and their entity id
+    passenger: Option<(Uuid, i32)>,
+    sent_position: Option<Vector3<f64>>,
+}
+
+impl Seat {
+    fn passengers(&self) -> Vec<i32> {
+        self.passenger.map(|(_, id)| id).into_iter().collect()
+    }
+}
+
+struct RenderedBone {
+    name: BoneName,
+    parent: Option<usize>,
+    /// Offset from the parent pivot, in blocks and display space
+    offset: Vector3<f32>,
+    /// Rest rotation in degrees, display space
+    rotation: Vector3<f32>,
+    /// Entity id and item model, `None` for bones without elements
+    display: Option<(i32, PackedBone)>,
+    /// Interaction entity of `hitbox` bones, with the bounds of the bone and whether it rotates
+    hitbox: Option<(i32, HitBoxBounds, bool)>,
+    seat: Option<Seat>,
+    pose: BonePose,
+    /// Pose given by the animation controller, added to `pose`
+    animated: BonePose,
+    sent: Option<DisplayTransform>,
+    sent_hitbox: Option<WorldHitBox>,
+    /// Hidden at load, by a script or by `set_visible`, its display is shrunk to nothing
+    hidden: bool,
+    /// Part of the skin drawn instead of the bone elements when the model has a skin
+    skin: Option<SkinPart>,
+    /// Gives the item drawn instead of the bone elements
+    item: Option<BoneItemMapper>,
+    /// Last item given by `item`
+    sent_item: Option<DisplayItem>,
+    /// Dye color set by a `tint` script or `set_tint`
+    tint: Option<i32>,
+}
+
+/// Renders one instance of a `ModelBlueprint` with one item display per bone.
+pub struct ModelTracker {
+    model: Arc<ModelBlueprint>,
+    /// World the model is in, only players of the same world see it
+    world: String,
+    position: Vector3<f64>,
+    bones: Vec<RenderedBone>,
+    index: HashMap<BoneName, usize>,
+    animation: AnimationController,
+    spawned: bool,
+    sent_position: Vector3<f64>,
+    update_interval: u32,
+    body_yaw: f32,
+    /// Pitch and yaw of the head, in Minecraft degrees
+    head_rotation: (f32, f32),
+    hit_listeners: Vec<HitListener>,
+    seat_listeners: Vec<SeatListener>,
+    /// Seat changes since the last `take_seat_events`
+    seat_events: Vec<SeatEvent>,
+    /// Commands run by the scripts since the last `take_commands`
+    commands: Vec<String>,
+    skin: Option<SkinProfile>,
+    equipment: Equipment,
+}
+
+impl ModelTracker {
+    pub fn new(
+        model: Arc<ModelBlueprint>,
+        world: impl Into<String>,
+        position: Vector3<f64>,
+    ) -> Self {
+        let mut bones = Vec::new();
+        for group in model.root_groups() {
+            Self::add_bone(&model, group, None, &mut bones);
+        }
+        let index = bones
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.name.clone(), i))
+            .collect();
+        Self {
+            animation: AnimationController::new(model.clone()),
+            model,
+            world: world.into(),
+            position,
+            bones,
+            index,
+            spawned: false,
+            sent_position: position,
+            update_interval: DEFAULT_UPDATE_INTERVAL_MILIS,
+            body_yaw: 0.0,
+            head_rotation: (0.0, 0.0),
+            hit_listeners: Vec::new(),
+            seat_listeners: Vec::new(),
+            seat_events: Vec::new(),
+            commands: Vec::new(),
+            skin: None,
+            equipment: Equipment::new(),
+        }
+    }
+
+    fn add_bone(
+        model: &ModelBlueprint,
+        group: &BlueprintGroup,
+        parent: Option<(usize, &BlueprintGroup)>,
+        bones: &mut Vec<RenderedBone>,
+    ) {
+        let offset = match parent {
+            Some((_, p)) => group.origin.sub(&p.origin),
+            None => group.origin.clone(),
+        };
+        let item = item::mapper_of(&group.name);
+        let packed = match item {
+            // L'objet garde sa taille, les éléments de l'os ne sont pas dessinés
+            Some(_) => Some(PackedBone {
+                bone: group.name.clone(),
+                item_model: String::new(),
+                scale: 1.0,
+            }),
+            None => packed_bone(model, group),
+        };
+        bones.push(RenderedBone {
+            name: group.name.clone(),
+            parent: parent.map(|(i, _)| i),
+            offset: math::transform_to_display(offset.to_block_scale().to_vec3()),
+            rotation: math::animation_to_display(group.rotation.to_vec3()),
+            display: packed.map(|p| (next_entity_id(), p)),
+            hitbox: is_hitbox(&group.name)
+                .then(|| HitBoxBounds::of(group))
+                .flatten()
+                .map(|bounds| (next_entity_id(), bounds, is_oriented(&group.name))),
+            seat: Self::seat_kind(&group.name).map(|kind| Seat {
+                entity_id: next_entity_id(),
+                kind,
+                passenger: None,
+                sent_position: None,
+            }),
+            pose: BonePose::EMPTY,
+            animated: BonePose::EMPTY,
+            sent: None,
+            sent_hitbox: None,
+            hidden: !group.visible(),
+            skin: SkinPart::of(&group.name),
+            item,
+            sent_item: None,
+            tint: None,
+        });
+        let index = bones.len() - 1;
+        for child in group.groups() {
+            Self::add_bone(model, child, Some((index, group)), bones);
+        }
+    }
+
+    fn seat_kind(name: &BoneName) -> Option<SeatKind> {
+        if name.has_tag(&BoneTag::SEAT) {
+            Some(SeatKind::Primary)
+        } else if name.has_tag(&BoneTag::SUB_SEAT) {
+            Some(SeatKind::Sub)
+        } else {
+            None
+        }
+    }
+
+    pub fn model(&self) -> &Arc<ModelBlueprint> {
+        &self.model
+    }
+
+    pub fn world(&self) -> &str {
+        &self.world
+    }
+
+    /// Moves the whole model to another world, viewers are updated on their next move.
+    pub fn set_world(&mut self, world: impl Into<String>) {
+        self.world = world.into();
+    }
+
+    pub fn position(&self) -> Vector3<f64> {
+        self.position
+    }
+
+    /// Moves the whole model, viewers are updated on the next `tick`.
+    pub fn set_position(&mut self, position: Vector3<f64>) {
+        self.position = position;
+    }
+
+    pub fn body_yaw(&self) -> f32 {
+        self.body_yaw
+    }
+
+    /// Turns the whole model around its origin, like the body of an entity.
+    pub fn set_body_yaw(&mut self, yaw: f32) {
+        self.body_yaw = yaw;
+    }
+
+    /// Rotates the `head` and `head_with_children` bones, `yaw` is absolute like the body yaw.
+    pub fn set_head_rotation(&mut self, pitch: f32, yaw: f32) {
+        self.head_rotation = (pitch, yaw);
+    }
+
+    pub fn is_spawned(&self) -> bool {
+        self.spawned
+    }
+
+    /// Ids of the item displays, hitboxes and seats of the model.
+    pub fn entity_ids(&self) -> Vec<i32> {
+        self.bones
+            .iter()
+            .flat_map(|b| {
+                let display = b.display.as_ref().map(|(id, _)| *id);
+                display
+                    .into_iter()
+                    .chain(b.hitbox.map(|(id, _, _)| id))
+                    .chain(b.seat.as_ref().map(|s| s.entity_id))
+            })
+            .collect()
+    }
+
+    fn display_ids(&self) -> Vec<i32> {
+        self.bones
+            .iter()
+            .filter_map(|b| b.display.as_ref().map(|(id, _)| *id))
+            .collect()
+    }
+
+    /// Current hitboxes of the model with their bone and interaction entity id.
+    pub fn hitboxes(&self) -> Vec<(&BoneName, i32, WorldHitBox)> {
+        self.bones
+            .iter()
+            .zip(self.transforms())
+            .filter_map(|(bone, transform)| {
+                let (entity_id, bounds, oriented) = bone.hitbox?;
+                Some((
+                    &bone.name,
+                    entity_id,
+                    WorldHitBox::new(self.position, &transform, &bounds, oriented),
+                ))
+            })
+            .collect()
+    }
+
+    pub fn hitbox_bone(&self, entity_id: i32) -> Option<&BoneName> {
+        self.bones
+            .iter()
+            .find(|b| b.hitbox.is_some_and(|(id, _, _)| id == entity_id))
+            .map(|b| &b.name)
+    }
+
+    /// Closest hitbox crossed by the ray, with its distance.
+    pub fn ray_cast(
+        &self,
+        origin: Vector3<f64>,
+        direction: Vector3<f64>,
+    ) -> Option<(&BoneName, f64)> {
+        self.hitboxes()
+            .into_iter()
+            .filter_map(|(bone, _, hitbox)| hitbox.ray_cast(origin, direction).map(|d| (bone, d)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    pub fn add_hit_listener(&mut self, listener: HitListener) {
+        self.hit_listeners.push(listener);
+    }
+
+    /// Hit on one of the hitboxes with the listeners of the model, dispatched by the caller.
+    pub fn hit(&self, event: HitEvent) -> PendingEvent<HitEvent> {
+        PendingEvent::new(event, &self.hit_listeners)
+    }
+
+    /// Seat bones with the player riding them.
+    pub fn seats(&self) -> Vec<(&BoneName, SeatKind, Option<Uuid>)> {
+        self.bones
+            .iter()
+            .filter_map(|b| {
+                let seat = b.seat.as_ref()?;
+                Some((&b.name, seat.kind, seat.passenger.map(|(p, _)| p)))
+            })
+            .collect()
+    }
+
+    /// Player on the primary seat, who controls the model.
+    pub fn driver(&self) -> Option<Uuid> {
+        self.seats()
+            .into_iter()
+            .find(|(_, kind, _)| *kind == SeatKind::Primary)
+            .and_then(|(_, _, player)| player)
+    }
+
+    pub fn seat_of(&self, player: &Uuid) -> Option<&BoneName> {
+        self.seats()
+            .into_iter()
+            .find(|(_, _, p)| p.as_ref() == Some(player))
+            .map(|(bone, _, _)| bone)
+    }
+
+    /// First free seat, the primary seats come first.
+    pub fn free_seat(&self) -> Option<&BoneName> {
+        let seats = self.seats();
+        [SeatKind::Primary, SeatKind::Sub]
+            .into_iter()
+            .find_map(|kind| {
+                seats
+                    .iter()
+                    .find(|(_, k, p)| *k == kind && p.is_none())
+                    .map(|(bone, _, _)| *bone)
+            })
+    }
+
+    pub fn add_seat_listener(&mut self, listener: SeatListener) {
+        self.seat_listeners.push(listener);
+    }
+
+    /// Mounts and dismounts since the last call with the seat listeners, dispatched by the caller.
+    pub fn take_seat_events(&mut self) -> Vec<PendingEvent<SeatEvent>> {
+        self.seat_events
+            .drain(..)
+            .map(|event| PendingEvent::new(event, &self.seat_listeners))
+            .collect()
+    }
+
+    /// Transition events of the animations since the last call, dispatched by the caller.
+    pub fn take_transition_events(&mut self) -> Vec<PendingEvent<TransitionEvent>> {
+        self.animation.take_transition_events()
+    }
+
+    /// Puts `player` on the seat `bone`, `entity_id` being the id of the player entity.
+    pub fn mount(
+        &mut self,
+        bone: &BoneName,
+        player: Uuid,
+        entity_id: i32,
+        sink: &mut impl PacketSink,
+    ) -> Result<(), SeatError> {
+        let seat = self
+            .index
+            .get(bone)
+            .and_then(|&i| self.bones[i].seat.as_mut())
+            .ok_or_else(|| SeatError::NotASeat(bone.name().to_string()))?;
+        if seat.passenger.is_some() {
+            return Err(SeatError::Occupied(bone.name().to_string()));
+        }
+        seat.passenger = Some((player, entity_id));
+        let kind = seat.kind;
+        if self.spawned {
+            sink.send(DisplayPacket::Passengers {
+                vehicle: seat.entity_id,
+                passengers: seat.passengers(),
+            });
+        }
+        self.seat_events.push(SeatEvent::Mount {
+            bone: bone.clone(),
+            player,
+            kind,
+        });
+        Ok(())
+    }
+
+    /// Takes `player` off their seat, returns the bone they were riding.
+    pub fn dismount(&mut self, player: &Uuid, sink: &mut impl PacketSink) -> Option<BoneName> {
+        let bone = self.bones.iter_mut().find(|b| {
+            b.seat
+                .as_ref()
+                .is_some_and(|s| s.passenger.is_some_and(|(p, _)| p == *player))
+        })?;
+        let seat = bone.seat.as_mut()?;
+        seat.passenger = None;
+        let kind = seat.kind;
+        if self.spawned {
+            sink.send(DisplayPacket::Passengers {
+                vehicle: seat.entity_id,
+                passengers: Vec::new(),
+            });
+        }
+        let name = bone.name.clone();
+        self.seat_events.push(SeatEvent::Dismount {
+            bone: name.clone(),
+            player: *player,
+            kind,
+        });
+        Some(name)
+    }
+
+    /// Milliseconds between two `tick` calls, used as the client interpolation duration.
+    pub fn set_update_interval(&mut self, milis: u32) {
+        self.update_interval = milis.max(1);
+    }
+
+    pub fn interpolation_ticks(&self) -> i32 {
+        (self.update_interval / math::MINECRAFT_TICK_MILIS as u32).max(1) as i32
+    }
+
+    pub fn animation(&self) -> &AnimationController {
+        &self.animation
+    }
+
+    /// Animations played on this instance, advanced by `update_interval` on every `tick`.
+    pub fn animation_mut(&mut self) -> &mut AnimationController {
+        &mut self.animation
+    }
+
+    fn apply_animation(&mut self) {
+        let mut poses = self.animation.poses();
+        for bone in &mut self.bones {
+            bone.animated = poses.remove(&bone.name).unwrap_or_default();
+        }
+    }
+
+    /// Manual pose of a bone, animations are added on top of it.
+    pub fn set_pose(&mut self, bone: &BoneName, pose: BonePose) {
+        if let Some(&i) = self.index.get(bone) {
+            self.bones[i].pose = pose;
+        }
+    }
+
+    pub fn pose(&self, bone: &BoneName) -> Option<BonePose> {
+        self.index.get(bone).map(|&i| self.bones[i].pose)
+    }
+
+    /// Shows or hides `bone`, and all its children when `recursive`. Only the bones changing are sent on the next `tick`.
+    pub fn set_visible(&mut self, bone: &BoneName, visible: bool, recursive: bool) {
+        let Some(&i) = self.index.get(bone) else {
+            return;
+        };
+        self.bones[i].hidden = !visible;
+        if recursive {
+            for child in self.descendants(i) {
+                self.bones[child].hidden = !visible;
+            }
+        }
+    }
+
+    pub fn is_visible(&self, bone: &BoneName) -> Option<bool> {
+        self.index.get(bone).map(|&i| !self.bones[i].hidden)
+    }
+
+    /// Indices of the bones under the bone `i`, they directly follow it since bones are added depth first.
+    fn descendants(&self, i: usize) -> Vec<usize> {
+        let mut descendants = Vec::new();
+        for j in i + 1..self.bones.len() {
+            let parent = self.bones[j].parent;
+            if parent != Some(i) && !parent.is_some_and(|p| descendants.contains(&p)) {
+                break;
+            }
+            descendants.push(j);
+        }
+        descendants
+    }
+
+    fn compose(
+        parent: &DisplayTransform,
+        translation: Vector3<f32>,
+        rotation: Quaternion,
+        scale: Vector3<f32>,
+    ) -> DisplayTransform {
+        DisplayTransform {
+            translation: parent.translation.add(
+                &parent.left_rotation.rotate(translation.multiply(
+                    parent.scale.x,
+                    parent.scale.y,
+                    parent.scale.z,
+                )),
+            ),
+            left_rotation: parent.left_rotation.mul(&rotation),
+            scale: scale.multiply(parent.scale.x, parent.scale.y, parent.scale.z),
+        }
+    }
+
+    /// Composes every bone with its parents, bones are stored parents first.
+    /// A `head` bone turns alone while a `head_with_children` bone carries its children.
+    fn transforms(&self) -> Vec<DisplayTransform> {
+        let root = DisplayTransform {
+            translation: Vector3::new(0.0, 0.0, 0.0),
+            left_rotation: Quaternion::from_euler(Vector3::new(0.0, -self.body_yaw, 0.0)),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        };
+        let (pitch, yaw) = self.head_rotation;
+        let head = Quaternion::from_euler(Vector3::new(pitch, self.body_yaw - yaw, 0.0));
+        // Transformation héritée par les enfants
+        let mut chain: Vec<DisplayTransform> = Vec::with_capacity(self.bones.len());
+        let mut result: Vec<DisplayTransform> = Vec::with_capacity(self.bones.len());
+        for bone in &self.bones {
+            let parent = bone.parent.map_or(&root, |p| &chain[p]);
+            let pose = bone.pose.add(&bone.animated);
+            let scale = Vector3::new(1.0, 1.0, 1.0).add(&pose.scale);
+            let translation = bone.offset.add(&pose.position);
+            let rotation = Quaternion::from_euler(bone.rotation.add(&pose.rotation));
+            let transform = Self::compose(parent, translation, rotation, scale);
+            let headed = Self::compose(parent, translation, head.mul(&rotation), scale);
+            if bone.name.has_tag(&BoneTag::HEAD_WITH_CHILDREN) {
+                chain.push(headed);
+                result.push(headed);
+            } else if bone.name.has_tag(&BoneTag::HEAD) {
+                chain.push(transform);
+                result.push(headed);
+            } else {
+                chain.push(transform);
+                result.push(transform);
+            }
+        }
+        result
+    }
+
+    /// Transform sent for a bone, the head drawing a skin `limb` is stretched to the size of the limb.
+    fn displayed(
+        transform: &DisplayTransform,
+        packed: &PackedBone,
+        hidden: bool,
+        limb: Option<SkinPart>,
+    ) -> DisplayTransform {
+        let scale = if hidden { 0.0 } else { packed.scale };
+        let [x, y, z] = limb.map_or([1.0; 3], |part| {
+            let head = SkinPart::Head.size()[0];
+            part.size().map(|s| s / head)
+        });
+        DisplayTransform {
+            scale: transform.scale.multiply(scale * x, scale * y, scale * z),
+            ..*transform
+        }
+    }
+
+    pub fn skin(&self) -> Option<&SkinProfile> {
+        self.skin.as_ref()
+    }
+
+    /// Draws the player part bones with the skin of `profile`, or with their own elements when `None`.
+    pub fn set_skin(&mut self, profile: Option<SkinProfile>, sink: &mut impl PacketSink) {
+        self.skin = profile;
+        if !self.spawned {
+            return;
+        }
+        for bone in self.bones.iter().filter(|b| b.skin.is_some()) {
+            if let Some((entity_id, packed)) = &bone.display {
+                sink.send(DisplayPacket::Item {
+                    entity_id: *entity_id,
+                    item: self.item(bone, packed),
+                });
+            }
+        }
+    }
+
+    pub fn equipment(&self) -> &Equipment {
+        &self.equipment
+    }
+
+    /// Items of the entity carrying the model, the item mapped bones showing a new item are sent on the next `tick`.
+    pub fn set_equipment(&mut self, equipment: Equipment) {
+        self.equipment = equipment;
+    }
+
+    /// Item of the display of `bone`, as already broadcast for the item mapped bones.
+    fn item(&self, bone: &RenderedBone, packed: &PackedBone) -> Option<DisplayItem> {
+        if bone.item.is_some() {
+            return bone.sent_item.clone();
+        }
+        Some(match (&self.skin, bone.skin) {
+            (Some(profile), Some(part)) => {
+                DisplayItem::skin(skin_item_model(part), part, profile.clone())
+            }
+            _ => DisplayItem {
+                dyed_color: bone.tint,
+                ..DisplayItem::model(packed.item_model.clone())
+            },
+        })
+    }
+
+    /// Dyes the elements of `bone`, or of every bone when `None`, removed with a `None` color.
+    pub fn set_tint(
+        &mut self,
+        bone: Option<&BoneName>,
+        color: Option<i32>,
+        sink: &mut impl PacketSink,
+    ) {
+        let indices = match bone {
+            Some(bone) => self.index.get(bone).copied().into_iter().collect(),
+            None => (0..self.bones.len()).collect::<Vec<_>>(),
+        };
+        for i in indices {
+            if self.bones[i].tint == color {
+                continue;
+            }
+            self.bones[i].tint = color;
+            let bone = &self.bones[i];
+            if self.spawned
+                && let Some((entity_id, packed)) = &bone.display
+            {
+                sink.send(DisplayPacket::Item {
+                    entity_id: *entity_id,
+                    item: self.item(bone, packed),
+                });
+            }
+        }
+    }
+
+    /// Sends the transformation of every bone to a new viewer, using the state already broadcast.
+    pub fn show(&self, sink: &mut impl PacketSink) {
+        if !self.spawned {
+            return;
+        }
+        for bone in &self.bones {
+            if let (Some((entity_id, packed)), Some(transform)) = (&bone.display, bone.sent) {
+                sink.send(DisplayPacket::Spawn {
+                    entity_id: *entity_id,
+                    position: self.sent_position,
+                    item: self.item(bone, packed),
+                    transform,
+                });
+            }
+            if let (Some((entity_id, _, _)), Some(hitbox)) = (bone.hitbox, bone.sent_hitbox) {
+                let (width, height) = hitbox.interaction_size();
+                sink.send(DisplayPacket::SpawnHitBox {
+                    entity_id,
+                    position: hitbox.interaction_position(),
+                    width,
+                    height,
+                });
+            }
+            if let Some(seat) = &bone.seat
+                && let Some(position) = seat.sent_position
+            {
+                sink.send(DisplayPacket::SpawnSeat {
+                    entity_id: seat.entity_id,
+                    position,
+                });
+                if seat.passenger.is_some() {
+                    sink.send(DisplayPacket::Passengers {
+                        vehicle: seat.entity_id,
+                        passengers: seat.passengers(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Removes the bones for one viewer, the tracker stays spawned for the others.
+    pub fn hide(&self, sink: &mut impl PacketSink) {
+        if !self.spawned {
+            return;
+        }
+        sink.send(DisplayPacket::Remove {
+            entity_ids: self.entity_ids(),
+        });
+    }
+
+    pub fn spawn(&mut self, sink: &mut impl PacketSink) {
+        self.apply_animation();
+        let transforms = self.transforms();
+        let skinned = self.skin.is_some();
+        for (bone, transform) in self.bones.iter_mut().zip(transforms) {
+            if let Some((_, packed)) = &bone.display {
+                let limb = bone.skin.filter(|_| skinned);
+                bone.sent = Some(Self::displayed(&transform, packed, bone.hidden, limb));
+            }
+            if let Some(mapper) = &bone.item {
+                bone.sent_item = mapper(&self.equipment);
+            }
+            if let Some((_, bounds, oriented)) = &bone.hitbox {
+                bone.sent_hitbox = Some(WorldHitBox::new(
+                    self.position,
+                    &transform,
+                    bounds,
+                    *oriented,
+                ));
+            }
+            if let Some(seat) = &mut bone.seat {
+                seat.sent_position = Some(Self::pivot_position(self.position, &transform));
+            }
+        }
+        self.spawned = true;
+        self.sent_position = self.position;
+        self.show(sink);
+    }
+
+    /// Sends the transformation of every bone whose composed transform changed since the last tick.
+    pub fn tick(&mut self, sink: &mut impl PacketSink) {
+        if !self.spawned {
+            return;
+        }
+        let interpolation_ticks = self.interpolation_ticks();
+        self.animation.advance(self.update_interval as f32 / 1000.0);
+        self.apply_animation();
+        if self.sent_position != self.position {
+            sink.send(DisplayPacket::Move {
+                entity_ids: self.display_ids(),
+                position: self.position,
+            });
+            self.sent_position = self.position;
+        }
+        let transforms = self.transforms();
+        self.run_scripts(&transforms, sink);
+        let skinned = self.skin.is_some();
+        for (bone, transform) in self.bones.iter_mut().zip(transforms) {
+            if let Some((entity_id, bounds, oriented)) = bone.hitbox {
+                let hitbox = WorldHitBox::new(self.position, &transform, &bounds, oriented);
+                Self::tick_hitbox(entity_id, bone.sent_hitbox, &hitbox, sink);
+                bone.sent_hitbox = Some(hitbox);
+            }
+            if let Some(seat) = &mut bone.seat {
+                let position = Self::pivot_position(self.position, &transform);
+                if seat
+                    .sent_position
+                    .is_none_or(|p| p.sub(&position).length_squared() > 1e-6)
+                {
+                    sink.send(DisplayPacket::Move {
+                        entity_ids: vec![seat.entity_id],
+                        position,
+                    });
+                    seat.sent_position = Some(position);
+                }
+            }
+            let Some((entity_id, packed)) = &bone.display else {
+                continue;
+            };
+            let limb = bone.skin.filter(|_| skinned);
+            let transform = Self::displayed(&transform, packed, bone.hidden, limb);
+            if bone.sent.is_some_and(|s| s.is_similar(&transform)) {
+                continue;
+            }
+            sink.send(DisplayPacket::Transform {
+                entity_id: *entity_id,
+                transform,
+                interpolation_ticks,
+            });
+            bone.sent = Some(transform);
+        }
+        self.tick_items(sink);
+    }
+
+    /// Sends the item mapped bones whose item changed with the equipment.
+    fn tick_items(&mut self, sink: &mut impl PacketSink) {
+        for bone in &mut self.bones {
+            let (Some(mapper), Some((entity_id, _))) = (&bone.item, &bone.display) else {
+                continue;
+            };
+            let item = mapper(&self.equipment);
+            if item != bone.sent_item {
+                sink.send(DisplayPacket::Item {
+                    entity_id: *entity_id,
+                    item: item.clone(),
+                });
+                bone.sent_item = item;
+            }
+        }
+    }
+
+    /// Runs the scripts reached by the animations, effects are played where the bones currently are.
+    fn run_scripts(&mut self, transforms: &[DisplayTransform], sink: &mut impl PacketSink) {
+        let scripts = self.animation.take_scripts();
+        if scripts.is_empty() {
+            return;
+        }
+        let bones = self
+            .bones
+            .iter()
+            .zip(transforms)
+            .map(|(bone, transform)| {
+                (
+                    bone.name.clone(),
+                    Self::pivot_position(self.position, transform),
+                )
+            })
+            .collect();
+        let mut source = RenderSource::new(self.position, bones);
+        for script in scripts {
+            script.run(&mut source);
+        }
+        for (effect, position) in source.take_effects() {
+            match effect {
+                ScriptEffect::Sound(name) => sink.send(DisplayPacket::Sound { name, position }),
+                ScriptEffect::Particle(name) => {
+                    sink.send(DisplayPacket::Particle { name, position })
+                }
+                ScriptEffect::Command(command) => self.commands.push(command),
+                ScriptEffect::Hide(bone) => self.set_visible(&bone, false, false),
+                ScriptEffect::Show(bone) => self.set_visible(&bone, true, false),
+                ScriptEffect::Tint { bone, color } => self.set_tint(bone.as_ref(), color, sink),
+            }
+        }
+    }
+
+    /// Commands run by the animation scripts since the last call, in order.
+    pub fn take_commands(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn pivot_position(position: Vector3<f64>, transform: &DisplayTransform) -> Vector3<f64> {
+        let t = transform.translation;
+        position.add(&Vector3::new(t.x as f64, t.y as f64, t.z as f64))
+    }
+
+    fn tick_hitbox(
+        entity_id: i32,
+        sent: Option<WorldHitBox>,
+        hitbox: &WorldHitBox,
+        sink: &mut impl PacketSink,
+    ) {
+        let (width, height) = hitbox.interaction_size();
+        let position = hitbox.interaction_position();
+        let (sent_size, sent_position) = sent
+            .map(|s| (Some(s.interaction_size()), Some(s.interaction_position())))
+            .unwrap_or_default();
+        if sent_position.is_none_or(|p| p.sub(&position).length_squared() > 1e-6) {
+            sink.send(DisplayPacket::Move {
+                entity_ids: vec![entity_id],
+                position,
+            });
+        }
+        if sent_size
+            .is_none_or(|(w, h)| !math::is_similar(w, width) || !math::is_similar(h, height))
+        {
+            sink.send(DisplayPacket::HitBoxSize {
+                entity_id,
+                width,
+                height,
+            });
+        }
+    }
+
+    /// Removes every entity of the model, its passengers are dismounted.
+    pub fn despawn(&mut self, sink: &mut impl PacketSink) {
+        self.hide(sink);
+        let passengers = self
+            .seats()
+            .into_iter()
+            .filter_map(|(_, _, player)| player)
+            .collect::<Vec<_>>();
+        for player in passengers {
+            self.dismount(&player, &mut Vec::new());
+        }
+        for bone in &mut self.bones {
+            bone.sent = None;
+            bone.sent_hitbox = None;
+            if let Some(seat) = &mut bone.seat {
+                seat.sent_position = None;
+            }
+        }
+        self.spawned = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use pumpkin_util::math::vector3::Vector3;
+    use uuid::Uuid;
+
+    use crate::bone::BoneTag;
+    use crate::bone::item::Equipment;
+    use crate::bone::item::EquipmentSlot;
+    use crate::bone::skin::SkinPart;
+    use crate::data::blueprint::BlueprintChildren;
+    use crate::data::fixture::blueprint;
+    use crate::data::fixture::bone;
+    use crate::data::fixture::load;
+    use crate::data::fixture::tag;
+    use crate::render::BonePose;
+    use crate::render::DisplayItem;
+    use crate::render::DisplayPacket;
+    use crate::render::SkinProfile;
+    use crate::render::hitbox::HitEvent;
+    use crate::render::hitbox::HitKind;
+
+    use super::ModelTracker;
+
+    fn head_tracker(head: BoneTag) -> ModelTracker {
+        let mut model = load();
+        tag(&mut model.group, "bone2", head);
+        ModelTracker::new(Arc::new(model), "overworld", Vector3::new(0.0, 64.0, 0.0))
+    }
+
+    #[test]
+    fn test_spawn_tick_despawn() {
+        let mut tracker = ModelTracker::new(blueprint(), "overworld", Vector3::new(0.0, 64.0, 0.0));
+        let mut packets = Vec::new();
+        tracker.tick(&mut packets);
+        assert!(packets.is_empty());
+
+        tracker.spawn(&mut packets);
+        assert_eq!(packets.len(), 4);
+        assert!(
+            packets
+                .iter()
+                .all(|p| matches!(p, DisplayPacket::Spawn { .. }))
+        );
+
+        packets.clear();
+        tracker.tick(&mut packets);
+        assert!(packets.is_empty());
+
+        tracker.despawn(&mut packets);
+        assert!(
+            matches!(&packets[0], DisplayPacket::Remove { entity_ids } if entity_ids.len() == 4)
+        );
+    }
+
+    #[test]
+    fn test_parent_pose_moves_children() {
+        let mut tracker = ModelTracker::new(blueprint(), "overworld", Vector3::new(0.0, 64.0, 0.0));
+        let mut packets = Vec::new();
+        tracker.spawn(&mut packets);
+        packets.clear();
+
+        tracker.set_pose(
+            &bone("bone2"),
+            BonePose {
+                rotation: Vector3::new(0.0, 0.0, 45.0),
+                ..BonePose::EMPTY
+            },
+        );
+        tracker.tick(&mut packets);
+        // bone2 et son enfant bone4
+        assert_eq!(packets.len(), 2);
+        assert!(packets.iter().all(|p| matches!(
+            p,
+            DisplayPacket::Transform {
+                interpolation_ticks: 1,
+                ..
+            }
+        )));
+    }
+
+    #[test]
+    fn test_head_rotation() {
+        for (head, moved) in [(BoneTag::HEAD, 1), (BoneTag::HEAD_WITH_CHILDREN, 2)] {
+            let mut tracker = head_tracker(head);
+            let mut packets = Vec::new();
+            tracker.spawn(&mut packets);
+            packets.clear();
+            tracker.set_head_rotation(30.0, 45.0);
+            tracker.tick(&mut packets);
+            assert_eq!(packets.len(), moved);
+        }
+    }
+
+    #[test]
+    fn test_follow_body() {
+        let mut tracker = ModelTracker::new(blueprint(), "overworld", Vector3::new(0.0, 64.0, 0.0));
+        let mut packets = Vec::new();
+        tracker.spawn(&mut packets);
+        packets.clear();
+
+        tracker.set_position(Vector3::new(2.0, 64.0, 0.0));
+        tracker.set_body_yaw(90.0);
+        tracker.tick(&mut packets);
+        assert!(matches!(
+            &packets[0],
+            DisplayPacket::Move { entity_ids, position } if entity_ids.len() == 4 && position.x == 2.0
+        ));
+        assert_eq!(packets.len(), 5);
+    }
+
+    #[test]
+    fn test_hitbox() {
+        let mut model = load();
+        tag(&mut model.group, "bone2", BoneTag::HITBOX);
+        let mut tracker =
+            ModelTracker::new(Arc::new(model), "overworld", Vector3::new(0.0, 64.0, 0.0));
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        tracker.add_hit_listener(Arc::new(move |event| {
+            assert_eq!(event.bone.name(), "bone2");
+            counter.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        let mut packets = Vec::new();
+        tracker.spawn(&mut packets);
+        assert_eq!(packets.len(), 5);
+        let Some(DisplayPacket::SpawnHitBox { entity_id, .. }) = packets
+            .iter()
+            .find(|p| matches!(p, DisplayPacket::SpawnHitBox { .. }))
+        else {
+            panic!("no hitbox spawned");
+        };
+        assert_eq!(tracker.entity_ids().len(), 5);
+
+        let (_, _, hitbox) = tracker.hitboxes()[0];
+        let hit = tracker.ray_cast(
+            hitbox.center.add(&Vector3::new(0.0, 10.0, 0.0)),
+            Vector3::new(0.0, -1.0, 0.0),
+        );
+        assert_eq!(hit.map(|(bone, _)| bone.name()), Some("bone2"));
+
+        tracker
+            .hit(HitEvent {
+                bone: tracker.hitbox_bone(*entity_id).unwrap().clone(),
+                player: Uuid::new_v4(),
+                kind: HitKind::Attack,
+            })
+            .dispatch();
+        assert_eq!(hits.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_animation_ticks() {
+        let mut tracker = ModelTracker::new(blueprint(), "overworld", Vector3::new(0.0, 64.0, 0.0));
+        let mut packets = Vec::new();
+        tracker.spawn(&mut packets);
+        packets.clear();
+
+        assert!(tracker.animation_mut().play("animation.model.new"));
+        tracker.tick(&mut packets);
+        assert_eq!(packets.len(), 4);
+        tracker.animation_mut().stop_all();
+        packets.clear();
+        tracker.tick(&mut packets);
+        assert_eq!(packets.len(), 4);
+        packets.clear();
+        tracker.tick(&mut packets);
+        assert!(packets.is_empty());
+    }
+
+    #[test]
+    fn test_skin() {
+        let mut tracker = head_tracker(BoneTag::PLAYER_HEAD);
+        let skin = SkinProfile {
+            id: Uuid::new_v4(),
+            name: "Steve".to_string(),
+            textures: "e30=".to_string(),
+            signature: None,
+        };
+        let mut packets = Vec::new();
+        tracker.set_skin(Some(skin.clone()), &mut packets);
+        assert!(packets.is_empty());
+
+        tracker.spawn(&mut packets);
+        let skinned: Vec<_> = packets
+            .iter()
+            .filter_map(|p| match p {
+                DisplayPacket::Spawn {
+                    item: Some(item), ..
+                } if item.profile.is_some() => Some(item),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            skinned,
+            vec![&DisplayItem::skin(
+                "modelblock:player/head",
+                SkinPart::Head,
+                skin
+            )]
+        );
+
+        packets.clear();
+        tracker.set_skin(None, &mut packets);
+        assert!(matches!(
+            &packets[..],
+            [DisplayPacket::Item { item: Some(item), .. }] if item.profile.is_none() && item.item == "minecraft:paper"
+        ));
+    }
+
+    #[test]
+    fn test_skin_limb() {
+        let mut tracker = head_tracker(BoneTag::PLAYER_RIGHT_ARM);
+        let skin = SkinProfile {
+            id: Uuid::new_v4(),
+            name: "Steve".to_string(),
+            textures: "e30=".to_string(),
+            signature: None,
+        };
+        let mut packets = Vec::new();
+        tracker.set_skin(Some(skin.clone()), &mut packets);
+        tracker.spawn(&mut packets);
+        let (item, scale) = packets
+            .iter()
+            .find_map(|p| match p {
+                DisplayPacket::Spawn {
+                    item: Some(item),
+                    transform,
+                    ..
+                } if item.profile.is_some() => Some((item, transform.scale)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            item,
+            &DisplayItem::skin("modelblock:player/right_arm", SkinPart::RightArm, skin)
+        );
+        // Le bras fait 4x12x4 pixels, la tête 8x8x8
+        assert!((scale.y / scale.x - 3.0).abs() < 1e-4);
+        assert!((scale.z - scale.x).abs() < 1e-4);
+
+        packets.clear();
+        tracker.set_skin(None, &mut packets);
+        tracker.tick(&mut packets);
+        let scale = packets
+            .iter()
+            .find_map(|p| match p {
+                DisplayPacket::Transform { transform, .. } => Some(transform.scale),
+                _ => None,
+            })
+            .unwrap();
+        assert!((scale.y - scale.x).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_item_mapper() {
+        let mut tracker = head_tracker(BoneTag::MAIN_HAND);
+        let mut packets = Vec::new();
+        tracker.spawn(&mut packets);
+        let hand = tracker.bones[tracker.index[&bone("bone2")]]
+            .display
+            .as_ref()
+            .unwrap()
+            .0;
+        assert!(packets.iter().any(|p| matches!(
+            p,
+            DisplayPacket::Spawn { entity_id, item: None, .. } if *entity_id == hand
+        )));
+
+        let sword = DisplayItem::new("minecraft:diamond_sword");
+        tracker.set_equipment(Equipment::new().with(EquipmentSlot::MainHand, sword.clone()));
+        packets.clear();
+        tracker.tick(&mut packets);
+        assert_eq!(
+            packets,
+            vec![DisplayPacket::Item {
+                entity_id: hand,
+                item: Some(sword.clone()),
+            }]
+        );
+
+        // Seul l'objet en main est affiché
+        tracker.set_equipment(
+            Equipment::new()
+                .with(EquipmentSlot::MainHand, sword.clone())
+                .with(
+                    EquipmentSlot::Head,
+                    DisplayItem::new("minecraft:iron_helmet"),
+                ),
+        );
+        packets.clear();
+        tracker.tick(&mut packets);
+        assert!(packets.is_empty());
+
+        // Un objet enchanté change d'apparence
+        let enchanted = DisplayItem {
+            enchantment_glint: Some(true),
+            ..sword
+        };
+        tracker.set_equipment(Equipment::new().with(EquipmentSlot::MainHand, enchanted.clone()));
+        packets.clear();
+        tracker.tick(&mut packets);
+        assert_eq!(
+            packets,
+            vec![DisplayPacket::Item {
+                entity_id: hand,
+                item: Some(enchanted),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_tint() {
+        let mut tracker = ModelTracker::new(blueprint(), "overworld", Vector3::new(0.0, 64.0, 0.0));
+        let mut packets = Vec::new();
+        tracker.spawn(&mut packets);
+        packets.clear();
+
+        tracker.set_tint(Some(&bone("bone2")), Some(0xff0000), &mut packets);
+        assert!(matches!(
+            &packets[..],
+            [DisplayPacket::Item { item: Some(item), .. }] if item.dyed_color == Some(0xff0000)
+        ));
+        packets.clear();
+        tracker.set_tint(None, Some(0xff0000), &mut packets);
+        assert_eq!(packets.len(), 3);
+        packets.clear();
+        tracker.set_tint(None, None, &mut packets);
+        assert!(packets.iter().all(|p| matches!(
+            p,
+            DisplayPacket::Item { item: Some(item), .. } if item.dyed_color.is_none()
+        )));
+    }
+
+    fn transformed(packets: &[DisplayPacket]) -> Vec<(i32, bool)> {
+        packets
+            .iter()
+            .filter_map(|p| match p {
+                DisplayPacket::Transform {
+                    entity_id,
+                    transform,
+                    ..
+                } => Some((*entity_id, transform.scale.x == 0.0)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_visibility() {
+        let mut tracker = ModelTracker::new(blueprint(), "overworld", Vector3::new(0.0, 64.0, 0.0));
+        let display = |tracker: &ModelTracker, name: &str| {
+            tracker.bones[tracker.index[&bone(name)]]
+                .display
+                .as_ref()
+                .unwrap()
+                .0
+        };
+        let (bone2, bone4) = (display(&tracker, "bone2"), display(&tracker, "bone4"));
+        let mut packets = Vec::new();
+        tracker.spawn(&mut packets);
+        packets.clear();
+
+        tracker.set_visible(&bone("bone2"), false, false);
+        assert_eq!(tracker.is_visible(&bone("bone2")), Some(false));
+        assert_eq!(tracker.is_visible(&bone("bone4")), Some(true));
+        tracker.tick(&mut packets);
+        assert_eq!(transformed(&packets), vec![(bone2, true)]);
+
+        // bone2 est déjà caché, seul bone4 change
+        packets.clear();
+        tracker.set_visible(&bone("bone2"), false, true);
+        tracker.tick(&mut packets);
+        assert_eq!(transformed(&packets), vec![(bone4, true)]);
+
+        packets.clear();
+        tracker.set_visible(&bone("bone2"), true, true);
+        tracker.set_visible(&bone("unknown"), false, true);
+        tracker.tick(&mut packets);
+        assert_eq!(transformed(&packets), vec![(bone2, false), (bone4, false)]);
+        assert_eq!(tracker.is_visible(&bone("unknown")), None);
+    }
+
+    #[test]
+    fn test_hidden_elements() {
+        fn hide(children: &mut [BlueprintChildren], raw_name: &str) {
+            for child in children {
+                if let BlueprintChildren::Group(group) = child {
+                    let hidden = group.name.raw_name() == raw_name;
+                    for child in &mut group.children {
+                        if let BlueprintChildren::Element(element) = child {
+                            element.visibility &= !hidden;
+                        }
+                    }
+                    hide(&mut group.children, raw_name);
+                }
+            }
+        }
+        let mut model = load();
+        hide(&mut model.group, "bone4");
+        let mut tracker =
+            ModelTracker::new(Arc::new(model), "overworld", Vector3::new(0.0, 64.0, 0.0));
+        assert_eq!(tracker.is_visible(&bone("bone4")), Some(false));
+        assert_eq!(tracker.is_visible(&bone("bone2")), Some(true));
+
+        let mut packets = Vec::new();
+        tracker.spawn(&mut packets);
+        let hidden = packets
+            .iter()
+            .filter(
+                |p| matches!(p, DisplayPacket::Spawn { transform, .. } if transform.scale.x == 0.0),
+            )
+            .count();
+        assert_eq!(hidden, 1);
+    }
+}
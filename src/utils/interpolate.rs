@@ -1,4 +1,5 @@
 use crate::utils::VectorPoint;
+use pumpkin_util::math::vector3::Vector3;
 use std::sync::Arc;
 
 pub trait VectorInterpolation {
@@ -7,23 +8,39 @@ pub trait VectorInterpolation {
     fn is_continuous(&self) -> bool {
         true
     }
+
+    /// Left bezier handle of the keyframe owning this interpolation, as `(time, value)`.
+    fn bezier_left(&self) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        None
+    }
 }
 
 pub fn default_interpolation() -> Arc<LinearInterpolation> {
     Arc::new(LinearInterpolation)
 }
 
+fn segment(points: &[VectorPoint], p2_index: usize) -> (VectorPoint, &VectorPoint) {
+    let p1 = if p2_index > 0 {
+        points[p2_index - 1].clone()
+    } else {
+        VectorPoint::empty()
+    };
+    (p1, &points[p2_index])
+}
+
+fn alpha(p1: &VectorPoint, p2: &VectorPoint, time: f32) -> f32 {
+    if p2.time == p1.time {
+        return 1.0;
+    }
+    ((time - p1.time) / (p2.time - p1.time)).clamp(0.0, 1.0)
+}
+
 pub struct LinearInterpolation;
 
 impl VectorInterpolation for LinearInterpolation {
     fn interpolate(&self, points: &[VectorPoint], p2_index: usize, time: f32) -> VectorPoint {
-        let p1 = if p2_index > 0 {
-            &points[p2_index - 1]
-        } else {
-            &VectorPoint::empty()
-        };
-        let p2 = &points[p2_index];
-        let t = (time - p1.time) / (p2.time - p1.time);
+        let (p1, p2) = segment(points, p2_index);
+        let t = alpha(&p1, p2, time);
         VectorPoint::new(
             p1.vector.lerp(&p2.vector, t),
             time,
@@ -31,3 +48,212 @@ impl VectorInterpolation for LinearInterpolation {
         )
     }
 }
+
+/// Holds the value of the previous keyframe until the next one is reached.
+pub struct StepInterpolation;
+
+impl VectorInterpolation for StepInterpolation {
+    fn interpolate(&self, points: &[VectorPoint], p2_index: usize, time: f32) -> VectorPoint {
+        let (p1, _) = segment(points, p2_index);
+        VectorPoint::new(p1.vector, time, Arc::new(StepInterpolation))
+    }
+
+    fn is_continuous(&self) -> bool {
+        false
+    }
+}
+
+/// Blockbench "smooth" interpolation, a uniform Catmull-Rom spline through the neighbouring keyframes.
+pub struct CatmullRomInterpolation;
+
+impl VectorInterpolation for CatmullRomInterpolation {
+    fn interpolate(&self, points: &[VectorPoint], p2_index: usize, time: f32) -> VectorPoint {
+        let (p1, p2) = segment(points, p2_index);
+        let p0 = if p2_index > 1 {
+            points[p2_index - 2].vector
+        } else {
+            p1.vector
+        };
+        let p3 = points
+            .get(p2_index + 1)
+            .map(|p| p.vector)
+            .unwrap_or(p2.vector);
+        let t = alpha(&p1, p2, time);
+        let axis = |a: f32, b: f32, c: f32, d: f32| {
+            let t2 = t * t;
+            let t3 = t2 * t;
+            0.5 * ((2.0 * b)
+                + (-a + c) * t
+                + (2.0 * a - 5.0 * b + 4.0 * c - d) * t2
+                + (-a + 3.0 * b - 3.0 * c + d) * t3)
+        };
+        VectorPoint::new(
+            Vector3::new(
+                axis(p0.x, p1.vector.x, p2.vector.x, p3.x),
+                axis(p0.y, p1.vector.y, p2.vector.y, p3.y),
+                axis(p0.z, p1.vector.z, p2.vector.z, p3.z),
+            ),
+            time,
+            Arc::new(CatmullRomInterpolation),
+        )
+    }
+}
+
+/// Cubic bezier between two keyframes, using the right handle of the first one
+/// and the left handle of the second one. Handles are offsets relative to their keyframe.
+pub struct BezierInterpolation {
+    pub left_time: Vector3<f32>,
+    pub left_value: Vector3<f32>,
+    pub right_time: Vector3<f32>,
+    pub right_value: Vector3<f32>,
+}
+
+impl BezierInterpolation {
+    pub fn new(
+        left_time: Vector3<f32>,
+        left_value: Vector3<f32>,
+        right_time: Vector3<f32>,
+        right_value: Vector3<f32>,
+    ) -> Self {
+        Self {
+            left_time,
+            left_value,
+            right_time,
+            right_value,
+        }
+    }
+}
+
+impl VectorInterpolation for BezierInterpolation {
+    fn interpolate(&self, points: &[VectorPoint], p2_index: usize, time: f32) -> VectorPoint {
+        let (p1, p2) = segment(points, p2_index);
+        let (left_time, left_value) = p2
+            .interpolation
+            .bezier_left()
+            .unwrap_or((Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0)));
+        let axis = |v1: f32, rt: f32, rv: f32, lt: f32, lv: f32, v2: f32| {
+            bezier(
+                time,
+                [p1.time, p1.time + rt, p2.time + lt, p2.time],
+                [v1, v1 + rv, v2 + lv, v2],
+            )
+        };
+        VectorPoint::new(
+            Vector3::new(
+                axis(
+                    p1.vector.x,
+                    self.right_time.x,
+                    self.right_value.x,
+                    left_time.x,
+                    left_value.x,
+                    p2.vector.x,
+                ),
+                axis(
+                    p1.vector.y,
+                    self.right_time.y,
+                    self.right_value.y,
+                    left_time.y,
+                    left_value.y,
+                    p2.vector.y,
+                ),
+                axis(
+                    p1.vector.z,
+                    self.right_time.z,
+                    self.right_value.z,
+                    left_time.z,
+                    left_value.z,
+                    p2.vector.z,
+                ),
+            ),
+            time,
+            default_interpolation(),
+        )
+    }
+
+    fn bezier_left(&self) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        Some((self.left_time, self.left_value))
+    }
+}
+
+fn cubic(p: [f32; 4], s: f32) -> f32 {
+    let r = 1.0 - s;
+    r * r * r * p[0] + 3.0 * r * r * s * p[1] + 3.0 * r * s * s * p[2] + s * s * s * p[3]
+}
+
+/// Evaluates a 2D cubic bezier curve at the abscissa `time`.
+/// The curve parameter is found by bisection since the time axis is monotonic between keyframes.
+fn bezier(time: f32, times: [f32; 4], values: [f32; 4]) -> f32 {
+    let (mut low, mut high) = (0.0f32, 1.0f32);
+    let mut s = 0.5;
+    for _ in 0..32 {
+        s = (low + high) * 0.5;
+        let x = cubic(times, s);
+        if (x - time).abs() < super::math::FRAME_EPSILON {
+            break;
+        }
+        if x < time {
+            low = s;
+        } else {
+            high = s;
+        }
+    }
+    cubic(values, s)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use pumpkin_util::math::vector3::Vector3;
+
+    use super::BezierInterpolation;
+    use super::CatmullRomInterpolation;
+    use super::StepInterpolation;
+    use super::VectorInterpolation;
+    use super::default_interpolation;
+    use crate::utils::VectorPoint;
+
+    fn point(
+        value: f32,
+        time: f32,
+        interpolation: Arc<dyn VectorInterpolation + Sync + Send>,
+    ) -> VectorPoint {
+        VectorPoint::new(Vector3::new(value, value, value), time, interpolation)
+    }
+
+    #[test]
+    fn test_step() {
+        let points = [
+            point(1.0, 0.0, Arc::new(StepInterpolation)),
+            point(5.0, 1.0, Arc::new(StepInterpolation)),
+        ];
+        let result = StepInterpolation.interpolate(&points, 1, 0.9);
+        assert_eq!(result.vector.x, 1.0);
+        assert!(!result.interpolation.is_continuous());
+    }
+
+    #[test]
+    fn test_catmull_rom_hits_keyframes() {
+        let points = [
+            point(0.0, 0.0, Arc::new(CatmullRomInterpolation)),
+            point(2.0, 1.0, Arc::new(CatmullRomInterpolation)),
+            point(1.0, 2.0, Arc::new(CatmullRomInterpolation)),
+        ];
+        let start = CatmullRomInterpolation.interpolate(&points, 1, 0.0);
+        let end = CatmullRomInterpolation.interpolate(&points, 1, 1.0);
+        assert!((start.vector.y - 0.0).abs() < 1e-5);
+        assert!((end.vector.y - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_bezier_without_handles_is_linear() {
+        let zero = Vector3::new(0.0, 0.0, 0.0);
+        let bezier = Arc::new(BezierInterpolation::new(zero, zero, zero, zero));
+        let points = [
+            point(0.0, 0.0, bezier.clone()),
+            point(4.0, 2.0, default_interpolation()),
+        ];
+        let result = bezier.interpolate(&points, 1, 1.0);
+        assert!((result.vector.z - 2.0).abs() < 1e-2);
+    }
+}
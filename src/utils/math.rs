@@ -32,15 +32,15 @@ pub fn valid_rotation_degree(rotation: f32) -> bool {
     VALID_ROTATION_DEGREES.contains(&rotation)
 }
 
-pub fn check_valid_degree(rotation: Float3) -> bool {
+pub fn check_valid_degree(rotation: &Float3) -> bool {
     let mut i = 0;
-    if rotation.x == 0. {
+    if rotation.x != 0. {
         i += 1
     }
-    if rotation.y == 0. {
+    if rotation.y != 0. {
         i += 1
     }
-    if rotation.z == 0. {
+    if rotation.z != 0. {
         i += 1
     }
     i < 2
@@ -49,6 +49,65 @@ pub fn check_valid_degree(rotation: Float3) -> bool {
         && valid_rotation_degree(rotation.z)
 }
 
+/// Rotation as sent in the `left_rotation` of a display entity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion::new(0.0, 0.0, 0.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    fn axis(axis: Vector3<f32>, degree: f32) -> Self {
+        let half = degree * DEGREE_TO_RADIANS / 2.0;
+        let sin = half.sin();
+        Self::new(axis.x * sin, axis.y * sin, axis.z * sin, half.cos())
+    }
+
+    /// Euler angles in degrees, applied in Blockbench's ZYX order.
+    pub fn from_euler(degrees: Vector3<f32>) -> Self {
+        Self::axis(Vector3::new(0.0, 0.0, 1.0), degrees.z)
+            .mul(&Self::axis(Vector3::new(0.0, 1.0, 0.0), degrees.y))
+            .mul(&Self::axis(Vector3::new(1.0, 0.0, 0.0), degrees.x))
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        Self::new(
+            self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+            self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+        )
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    pub fn rotate(&self, vector: Vector3<f32>) -> Vector3<f32> {
+        let v = Self::new(vector.x, vector.y, vector.z, 0.0);
+        let r = self.mul(&v).mul(&self.conjugate());
+        Vector3::new(r.x, r.y, r.z)
+    }
+
+    pub fn is_similar(&self, other: &Self) -> bool {
+        // q et -q représentent la même rotation
+        let dot = self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w;
+        is_similar(dot.abs(), 1.0)
+    }
+}
+
+pub fn is_similar_vec(a: &Vector3<f32>, b: &Vector3<f32>) -> bool {
+    is_similar(a.x, b.x) && is_similar(a.y, b.y) && is_similar(a.z, b.z)
+}
+
 pub fn animation_to_display(vector: Vector3<f32>) -> Vector3<f32> {
     Vector3::new(vector.x, -vector.y, -vector.z)
 }
@@ -56,3 +115,30 @@ pub fn animation_to_display(vector: Vector3<f32>) -> Vector3<f32> {
 pub fn transform_to_display(vector: Vector3<f32>) -> Vector3<f32> {
     Vector3::new(vector.x, vector.y, -vector.z)
 }
+
+#[cfg(test)]
+mod test {
+    use pumpkin_util::math::vector3::Vector3;
+
+    use super::Quaternion;
+    use super::check_valid_degree;
+    use super::is_similar_vec;
+    use crate::data::raw::float3::Float3;
+
+    #[test]
+    fn test_check_valid_degree() {
+        assert!(check_valid_degree(&Float3::new(0., 0., 0.)));
+        assert!(check_valid_degree(&Float3::new(0., -45., 0.)));
+        assert!(check_valid_degree(&Float3::new(22.5, 0., 0.)));
+        assert!(!check_valid_degree(&Float3::new(10., 0., 0.)));
+        assert!(!check_valid_degree(&Float3::new(22.5, 0., 22.5)));
+    }
+
+    #[test]
+    fn test_quaternion_rotate() {
+        let q = Quaternion::from_euler(Vector3::new(0., 90., 0.));
+        let rotated = q.rotate(Vector3::new(1., 0., 0.));
+        assert!(is_similar_vec(&rotated, &Vector3::new(0., 0., -1.)));
+        assert!(Quaternion::from_euler(Vector3::new(0., 0., 0.)).is_similar(&Quaternion::IDENTITY));
+    }
+}
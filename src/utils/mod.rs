@@ -11,6 +11,7 @@ use crate::data::blueprint::animation::AnimationPoint;
 pub mod collection;
 pub mod interpolate;
 pub mod math;
+pub mod molang;
 
 #[derive(Clone)]
 pub struct VectorPoint {
@@ -23,6 +24,13 @@ fn points(set: &mut BTreeSet<OrderedFloat<f32>>, points: &[VectorPoint]) {
     for point in points {
         set.insert(OrderedFloat(point.time));
     }
+    // A discontinuous keyframe holds its value until the very end of its segment,
+    // so sample just before the next keyframe to produce a hard cut.
+    for pair in points.windows(2) {
+        if !pair[0].interpolation.is_continuous() {
+            set.insert(OrderedFloat(pair[1].time - math::FRAME_EPSILON));
+        }
+    }
 }
 
 pub fn sum(
@@ -34,22 +42,22 @@ pub fn sum(
     let mut set: BTreeSet<OrderedFloat<f32>> = BTreeSet::new();
     set.insert(OrderedFloat(0.));
     set.insert(OrderedFloat(lenght));
-    points(&mut set, &position);
-    points(&mut set, &rotation);
-    points(&mut set, &scale);
+    points(&mut set, position);
+    points(&mut set, rotation);
+    points(&mut set, scale);
     sum_with_set(position, rotation, scale, set)
 }
 
-fn sum_with_set(
+pub fn sum_with_set(
     position: &[VectorPoint],
     rotation: &[VectorPoint],
     scale: &[VectorPoint],
     set: BTreeSet<OrderedFloat<f32>>,
 ) -> Vec<AnimationPoint> {
     let mut points = Vec::new();
-    let pp = put_point(&position, &set);
-    let rp = put_point(&rotation, &set);
-    let sp = put_point(&scale, &set);
+    let pp = put_point(position, &set);
+    let rp = put_point(rotation, &set);
+    let sp = put_point(scale, &set);
     for ((position, rotation), scale) in pp.into_iter().zip(rp).zip(sp) {
         points.push(AnimationPoint {
             position,
@@ -60,11 +68,33 @@ fn sum_with_set(
     points
 }
 
+/// Value of the channel `vectors` at `time`, interpolated like the points baked by `sum_with_set`.
+pub fn point_at(vectors: &[VectorPoint], time: f32) -> Option<Vector3<f32>> {
+    let first = vectors.first()?;
+    let last = &vectors[vectors.len() - 1];
+    if vectors.len() < 2 {
+        return Some(first.vector);
+    }
+    if time > last.time {
+        return Some(last.vector);
+    }
+    let i = vectors.iter().position(|p| p.time >= time)?;
+    if vectors[i].time == time {
+        return Some(vectors[i].vector);
+    }
+    // Avant la première clé, le segment part de zéro comme dans put_point
+    let interpolation = match i {
+        0 => VectorPoint::empty().interpolation,
+        _ => vectors[i - 1].interpolation.clone(),
+    };
+    Some(interpolation.interpolate(vectors, i, time).vector)
+}
+
 fn put_point(vectors: &[VectorPoint], points: &BTreeSet<OrderedFloat<f32>>) -> Vec<VectorPoint> {
     let mut new_points = Vec::with_capacity(points.len());
 
     if vectors.len() < 2 {
-        let first = vectors.get(0).cloned().unwrap_or_else(VectorPoint::empty);
+        let first = vectors.first().cloned().unwrap_or_else(VectorPoint::empty);
         for &time in points {
             new_points.push(VectorPoint::new(
                 first.vector,
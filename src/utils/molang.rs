@@ -0,0 +1,717 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::math::DEGREE_TO_RADIANS;
+use super::math::RADIANS_TO_DEGREE;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MolangError {
+    pub source: String,
+    pub message: String,
+}
+
+impl Display for MolangError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid molang \"{}\": {}", self.source, self.message)
+    }
+}
+
+impl std::error::Error for MolangError {}
+
+/// Values the queries and variables of an expression are read from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MolangContext {
+    /// Seconds since the start of the animation
+    pub anim_time: f32,
+    /// Seconds since the model was created
+    pub life_time: f32,
+    pub variables: HashMap<String, f32>,
+}
+
+impl MolangContext {
+    pub fn at(anim_time: f32) -> Self {
+        Self {
+            anim_time,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Query {
+    AnimTime,
+    LifeTime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Function {
+    Abs,
+    Sin,
+    Cos,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Sqrt,
+    Pow,
+    Exp,
+    Ln,
+    Floor,
+    Ceil,
+    Round,
+    Trunc,
+    Clamp,
+    Lerp,
+    Min,
+    Max,
+    Mod,
+    Sign,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "abs" => Function::Abs,
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "asin" => Function::Asin,
+            "acos" => Function::Acos,
+            "atan" => Function::Atan,
+            "atan2" => Function::Atan2,
+            "sqrt" => Function::Sqrt,
+            "pow" => Function::Pow,
+            "exp" => Function::Exp,
+            "ln" => Function::Ln,
+            "floor" => Function::Floor,
+            "ceil" => Function::Ceil,
+            "round" => Function::Round,
+            "trunc" => Function::Trunc,
+            "clamp" => Function::Clamp,
+            "lerp" => Function::Lerp,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "mod" => Function::Mod,
+            "sign" => Function::Sign,
+            _ => return None,
+        })
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            Function::Atan2 | Function::Pow | Function::Min | Function::Max | Function::Mod => 2,
+            Function::Clamp | Function::Lerp => 3,
+            _ => 1,
+        }
+    }
+
+    /// Trigonometry works in degrees, as in Bedrock.
+    fn apply(&self, a: &[f32]) -> f32 {
+        match self {
+            Function::Abs => a[0].abs(),
+            Function::Sin => (a[0] * DEGREE_TO_RADIANS).sin(),
+            Function::Cos => (a[0] * DEGREE_TO_RADIANS).cos(),
+            Function::Asin => a[0].asin() * RADIANS_TO_DEGREE,
+            Function::Acos => a[0].acos() * RADIANS_TO_DEGREE,
+            Function::Atan => a[0].atan() * RADIANS_TO_DEGREE,
+            Function::Atan2 => a[0].atan2(a[1]) * RADIANS_TO_DEGREE,
+            Function::Sqrt => a[0].sqrt(),
+            Function::Pow => a[0].powf(a[1]),
+            Function::Exp => a[0].exp(),
+            Function::Ln => a[0].ln(),
+            Function::Floor => a[0].floor(),
+            Function::Ceil => a[0].ceil(),
+            Function::Round => a[0].round(),
+            Function::Trunc => a[0].trunc(),
+            Function::Clamp => a[0].max(a[1]).min(a[2]),
+            Function::Lerp => a[0] + (a[1] - a[0]) * a[2],
+            Function::Min => a[0].min(a[1]),
+            Function::Max => a[0].max(a[1]),
+            Function::Mod => a[0] % a[1],
+            Function::Sign => {
+                if a[0] < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+impl Operator {
+    fn apply(&self, a: f32, b: f32) -> f32 {
+        let flag = |b: bool| if b { 1.0 } else { 0.0 };
+        match self {
+            Operator::Add => a + b,
+            Operator::Sub => a - b,
+            Operator::Mul => a * b,
+            Operator::Div => a / b,
+            Operator::Less => flag(a < b),
+            Operator::LessEqual => flag(a <= b),
+            Operator::Greater => flag(a > b),
+            Operator::GreaterEqual => flag(a >= b),
+            Operator::Equal => flag(a == b),
+            Operator::NotEqual => flag(a != b),
+            Operator::And => flag(a != 0.0 && b != 0.0),
+            Operator::Or => flag(a != 0.0 || b != 0.0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expression {
+    Constant(f32),
+    Query(Query),
+    Variable(String),
+    Negate(Box<Expression>),
+    Not(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+    Ternary(Box<Expression>, Box<Expression>, Box<Expression>),
+    Call(Function, Vec<Expression>),
+    Assign(String, Box<Expression>),
+    Return(Box<Expression>),
+}
+
+impl Expression {
+    fn constant(&self) -> Option<f32> {
+        match self {
+            Expression::Constant(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Replaces every sub-expression that doesn't read the context by its value.
+    fn fold(self) -> Self {
+        let folded = match self {
+            Expression::Negate(a) => Expression::Negate(Box::new(a.fold())),
+            Expression::Not(a) => Expression::Not(Box::new(a.fold())),
+            Expression::Binary(op, a, b) => {
+                Expression::Binary(op, Box::new(a.fold()), Box::new(b.fold()))
+            }
+            Expression::Ternary(c, a, b) => {
+                let c = c.fold();
+                match c.constant() {
+                    Some(c) if c != 0.0 => return a.fold(),
+                    Some(_) => return b.fold(),
+                    None => {
+                        Expression::Ternary(Box::new(c), Box::new(a.fold()), Box::new(b.fold()))
+                    }
+                }
+            }
+            Expression::Call(function, args) => {
+                Expression::Call(function, args.into_iter().map(Expression::fold).collect())
+            }
+            Expression::Assign(name, a) => return Expression::Assign(name, Box::new(a.fold())),
+            Expression::Return(a) => return Expression::Return(Box::new(a.fold())),
+            other => return other,
+        };
+        match &folded {
+            Expression::Negate(_)
+            | Expression::Not(_)
+            | Expression::Binary(..)
+            | Expression::Call(..) => {
+                let mut children = Vec::new();
+                folded.children(&mut children);
+                if children.iter().all(|c| c.constant().is_some()) {
+                    Expression::Constant(
+                        folded.eval(&MolangContext::default(), &mut HashMap::new()),
+                    )
+                } else {
+                    folded
+                }
+            }
+            _ => folded,
+        }
+    }
+
+    fn children<'a>(&'a self, out: &mut Vec<&'a Expression>) {
+        match self {
+            Expression::Negate(a) | Expression::Not(a) => out.push(a),
+            Expression::Binary(_, a, b) => out.extend([a.as_ref(), b.as_ref()]),
+            Expression::Call(_, args) => out.extend(args),
+            _ => {}
+        }
+    }
+
+    fn eval(&self, context: &MolangContext, locals: &mut HashMap<String, f32>) -> f32 {
+        match self {
+            Expression::Constant(value) => *value,
+            Expression::Query(Query::AnimTime) => context.anim_time,
+            Expression::Query(Query::LifeTime) => context.life_time,
+            Expression::Variable(name) => locals
+                .get(name)
+                .or_else(|| context.variables.get(name))
+                .copied()
+                .unwrap_or(0.0),
+            Expression::Negate(a) => -a.eval(context, locals),
+            Expression::Not(a) => {
+                if a.eval(context, locals) == 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Expression::Binary(op, a, b) => {
+                op.apply(a.eval(context, locals), b.eval(context, locals))
+            }
+            Expression::Ternary(c, a, b) => {
+                if c.eval(context, locals) != 0.0 {
+                    a.eval(context, locals)
+                } else {
+                    b.eval(context, locals)
+                }
+            }
+            Expression::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| a.eval(context, locals))
+                    .collect::<Vec<_>>();
+                function.apply(&args)
+            }
+            Expression::Assign(name, a) => {
+                let value = a.eval(context, locals);
+                locals.insert(name.clone(), value);
+                value
+            }
+            Expression::Return(a) => a.eval(context, locals),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 20] = [
+    "&&", "||", "==", "!=", "<=", ">=", "+", "-", "*", "/", "<", ">", "!", "?", ":", "(", ")", ",",
+    ";", "=",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_digit() || c == '.' {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let number = rest[..end]
+                .parse::<f32>()
+                .map_err(|_| format!("invalid number {}", &rest[..end]))?;
+            tokens.push(Token::Number(number));
+            // Suffixe `f` toléré, comme dans les fichiers Bedrock
+            rest = rest[end..].strip_prefix(['f', 'F']).unwrap_or(&rest[end..]);
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '.')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Name(rest[..end].to_ascii_lowercase()));
+            rest = &rest[end..];
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|s| rest.starts_with(**s))
+                .ok_or_else(|| format!("unexpected character '{c}'"))?;
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("expected '{symbol}'"))
+        }
+    }
+
+    /// Statements separated by `;`, the value is the one of the `return` or of the last statement.
+    fn statements(&mut self) -> Result<Vec<Expression>, String> {
+        let mut statements = Vec::new();
+        while self.peek().is_some() {
+            if self.eat(";") {
+                continue;
+            }
+            statements.push(self.statement()?);
+            if self.peek().is_some() {
+                self.expect(";")?;
+            }
+        }
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Expression, String> {
+        if let Some(Token::Name(name)) = self.peek() {
+            if name == "return" {
+                self.index += 1;
+                return Ok(Expression::Return(Box::new(self.ternary()?)));
+            }
+            if let Some(variable) = variable_name(name)
+                && matches!(self.tokens.get(self.index + 1), Some(Token::Symbol("=")))
+            {
+                self.index += 2;
+                return Ok(Expression::Assign(variable, Box::new(self.ternary()?)));
+            }
+        }
+        self.ternary()
+    }
+
+    fn ternary(&mut self) -> Result<Expression, String> {
+        let condition = self.binary(0)?;
+        if !self.eat("?") {
+            return Ok(condition);
+        }
+        let a = self.ternary()?;
+        self.expect(":")?;
+        let b = self.ternary()?;
+        Ok(Expression::Ternary(
+            Box::new(condition),
+            Box::new(a),
+            Box::new(b),
+        ))
+    }
+
+    /// Operators by increasing precedence.
+    fn binary(&mut self, level: usize) -> Result<Expression, String> {
+        const LEVELS: [&[(&str, Operator)]; 6] = [
+            &[("||", Operator::Or)],
+            &[("&&", Operator::And)],
+            &[("==", Operator::Equal), ("!=", Operator::NotEqual)],
+            &[
+                ("<=", Operator::LessEqual),
+                (">=", Operator::GreaterEqual),
+                ("<", Operator::Less),
+                (">", Operator::Greater),
+            ],
+            &[("+", Operator::Add), ("-", Operator::Sub)],
+            &[("*", Operator::Mul), ("/", Operator::Div)],
+        ];
+        let Some(operators) = LEVELS.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1)?;
+        'outer: loop {
+            for (symbol, operator) in operators.iter() {
+                if self.eat(symbol) {
+                    let right = self.binary(level + 1)?;
+                    left = Expression::Binary(*operator, Box::new(left), Box::new(right));
+                    continue 'outer;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.eat("-") {
+            return Ok(Expression::Negate(Box::new(self.unary()?)));
+        }
+        if self.eat("!") {
+            return Ok(Expression::Not(Box::new(self.unary()?)));
+        }
+        self.eat("+");
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression, String> {
+        let token = self.peek().cloned().ok_or("unexpected end")?;
+        self.index += 1;
+        match token {
+            Token::Number(value) => Ok(Expression::Constant(value)),
+            Token::Symbol("(") => {
+                let expression = self.ternary()?;
+                self.expect(")")?;
+                Ok(expression)
+            }
+            Token::Name(name) => self.name(&name),
+            Token::Symbol(symbol) => Err(format!("unexpected '{symbol}'")),
+        }
+    }
+
+    fn name(&mut self, name: &str) -> Result<Expression, String> {
+        if let Some(variable) = variable_name(name) {
+            return Ok(Expression::Variable(variable));
+        }
+        if let Some(query) = name
+            .strip_prefix("query.")
+            .or_else(|| name.strip_prefix("q."))
+        {
+            return Ok(match query {
+                "anim_time" => Expression::Query(Query::AnimTime),
+                "life_time" => Expression::Query(Query::LifeTime),
+                _ => {
+                    log::warn!("Unsupported molang query {name}, evaluated as 0");
+                    Expression::Constant(0.0)
+                }
+            });
+        }
+        if name == "math.pi" {
+            return Ok(Expression::Constant(std::f32::consts::PI));
+        }
+        let Some(function) = name.strip_prefix("math.").and_then(Function::from_name) else {
+            // Les arguments sont lus pour continuer l'analyse, `math.random` ou `foo.bar` vaut 0
+            if self.eat("(") {
+                self.arguments()?;
+            }
+            log::warn!("Unsupported molang name {name}, evaluated as 0");
+            return Ok(Expression::Constant(0.0));
+        };
+        self.expect("(")?;
+        let args = self.arguments()?;
+        if args.len() != function.arity() {
+            return Err(format!(
+                "{name} takes {} arguments, got {}",
+                function.arity(),
+                args.len()
+            ));
+        }
+        Ok(Expression::Call(function, args))
+    }
+
+    /// Arguments of a call, after its opening parenthesis.
+    fn arguments(&mut self) -> Result<Vec<Expression>, String> {
+        let mut args = Vec::new();
+        if !self.eat(")") {
+            loop {
+                args.push(self.ternary()?);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// `variable.x` and `temp.x` share the same namespace, keyed by `x`.
+fn variable_name(name: &str) -> Option<String> {
+    ["variable.", "v.", "temp.", "t."]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .map(str::to_string)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Constant(f32),
+    Dynamic {
+        source: String,
+        statements: Arc<Vec<Expression>>,
+    },
+}
+
+/// Molang expression of a keyframe value, folded to a constant when it doesn't read any query or variable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Molang(Value);
+
+impl Molang {
+    pub const fn constant(value: f32) -> Self {
+        Self(Value::Constant(value))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, MolangError> {
+        let error = |message: String| MolangError {
+            source: source.to_string(),
+            message,
+        };
+        let trimmed = source.trim();
+        if trimmed.is_empty() {
+            return Ok(Self::constant(0.0));
+        }
+        if let Ok(value) = trimmed.parse::<f32>() {
+            return Ok(Self::constant(value));
+        }
+        let mut parser = Parser {
+            tokens: tokenize(trimmed).map_err(error)?,
+            index: 0,
+        };
+        let statements = parser
+            .statements()
+            .map_err(error)?
+            .into_iter()
+            .map(Expression::fold)
+            .collect::<Vec<_>>();
+        let constant = match statements.as_slice() {
+            [] => Some(0.0),
+            [Expression::Constant(value)] => Some(*value),
+            [Expression::Return(value)] => value.constant(),
+            _ => None,
+        };
+        Ok(Self(match constant {
+            Some(value) => Value::Constant(value),
+            None => Value::Dynamic {
+                source: source.to_string(),
+                statements: Arc::new(statements),
+            },
+        }))
+    }
+
+    pub fn is_constant(&self) -> bool {
+        matches!(self.0, Value::Constant(_))
+    }
+
+    /// Value of a folded expression, `None` if it reads the context.
+    pub fn as_constant(&self) -> Option<f32> {
+        match self.0 {
+            Value::Constant(value) => Some(value),
+            Value::Dynamic { .. } => None,
+        }
+    }
+
+    pub fn eval(&self, context: &MolangContext) -> f32 {
+        let statements = match &self.0 {
+            Value::Constant(value) => return *value,
+            Value::Dynamic { statements, .. } => statements,
+        };
+        let mut locals = HashMap::new();
+        let mut value = 0.0;
+        for statement in statements.iter() {
+            value = statement.eval(context, &mut locals);
+            if matches!(statement, Expression::Return(_)) {
+                break;
+            }
+        }
+        value
+    }
+}
+
+impl Default for Molang {
+    fn default() -> Self {
+        Self::constant(0.0)
+    }
+}
+
+impl From<f32> for Molang {
+    fn from(value: f32) -> Self {
+        Self::constant(value)
+    }
+}
+
+impl Serialize for Molang {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.0 {
+            Value::Constant(value) => serializer.serialize_str(&value.to_string()),
+            Value::Dynamic { source, .. } => serializer.serialize_str(source),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Molang {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Blockbench écrit les valeurs en texte, mais les nombres restent acceptés
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(f32),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(value) => Ok(Molang::constant(value)),
+            Raw::Text(source) => Molang::parse(&source).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Molang;
+    use super::MolangContext;
+
+    fn eval(source: &str, context: &MolangContext) -> f32 {
+        Molang::parse(source).unwrap().eval(context)
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let constant = |source: &str| Molang::parse(source).unwrap().as_constant();
+        assert_eq!(constant("12.5"), Some(12.5));
+        assert_eq!(constant(""), Some(0.0));
+        assert_eq!(constant("-2 * (3 + 1)"), Some(-8.0));
+        assert_eq!(constant("math.max(1, 2) > 1 ? 10 : 20"), Some(10.0));
+        assert!((constant("math.sin(90) * 5").unwrap() - 5.0).abs() < 1e-5);
+        assert_eq!(constant("math.sin(query.anim_time * 90)"), None);
+    }
+
+    #[test]
+    fn test_dynamic() {
+        let context = MolangContext {
+            anim_time: 1.0,
+            life_time: 4.0,
+            variables: [("speed".to_string(), 3.0)].into(),
+        };
+        assert!((eval("math.sin(query.anim_time*90)*5", &context) - 5.0).abs() < 1e-5);
+        assert_eq!(eval("q.life_time / 2", &context), 2.0);
+        assert_eq!(eval("variable.speed * 2 + v.missing", &context), 6.0);
+        assert_eq!(eval("Query.Anim_Time == 1 && !0", &context), 1.0);
+        assert_eq!(
+            eval("t.a = q.anim_time + 1; return t.a * 2;", &context),
+            4.0
+        );
+        assert_eq!(eval("math.clamp(q.life_time, 0, 3)", &context), 3.0);
+    }
+
+    #[test]
+    fn test_unsupported_is_zero() {
+        let context = MolangContext::at(1.0);
+        assert_eq!(eval("query.is_on_ground + 1", &context), 1.0);
+        assert_eq!(eval("math.random(0, 10) + q.anim_time", &context), 1.0);
+        assert_eq!(eval("math.nope()", &context), 0.0);
+        assert_eq!(eval("unknown + 2", &context), 2.0);
+        assert_eq!(eval("foo.bar(1, 2) * 3 + math.nope", &context), 0.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(Molang::parse("math.pow(1)").is_err());
+        assert!(Molang::parse("math.random(1").is_err());
+        assert!(Molang::parse("(1 + 2").is_err());
+        assert!(Molang::parse("1 $ 2").is_err());
+    }
+}